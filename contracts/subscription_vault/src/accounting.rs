@@ -0,0 +1,73 @@
+//! Liability accounting and solvency reporting.
+//!
+//! **PRs that only change how liabilities are tracked or reported should edit this file only.**
+//!
+//! The contract owes two kinds of funds:
+//! - **Prepaid liabilities**: the sum of every subscription's `prepaid_balance`.
+//! - **Merchant liabilities**: the sum of every merchant's claimable balance.
+//!
+//! Both totals are maintained as running counters updated on every balance
+//! mutation, so the solvency check is O(1) regardless of subscription count.
+//! Anything the contract holds above these liabilities is surplus and is the
+//! only amount admin recovery may move.
+
+use crate::admin::get_token;
+use crate::safe_math::{safe_add, safe_add_balance, safe_sub, safe_sub_balance};
+use crate::types::{DataKey, Error, SolvencyReport};
+use soroban_sdk::Env;
+
+fn get_total(env: &Env, key: &DataKey) -> i128 {
+    env.storage().instance().get(key).unwrap_or(0)
+}
+
+fn increase(env: &Env, key: DataKey, amount: i128) -> Result<(), Error> {
+    let total = safe_add_balance(get_total(env, &key), amount)?;
+    env.storage().instance().set(&key, &total);
+    Ok(())
+}
+
+fn decrease(env: &Env, key: DataKey, amount: i128) -> Result<(), Error> {
+    let total = safe_sub_balance(get_total(env, &key), amount)?;
+    env.storage().instance().set(&key, &total);
+    Ok(())
+}
+
+/// Records `amount` added to some subscription's `prepaid_balance`.
+pub fn increase_prepaid_liabilities(env: &Env, amount: i128) -> Result<(), Error> {
+    increase(env, DataKey::TotalPrepaid, amount)
+}
+
+/// Records `amount` removed from some subscription's `prepaid_balance`.
+pub fn decrease_prepaid_liabilities(env: &Env, amount: i128) -> Result<(), Error> {
+    decrease(env, DataKey::TotalPrepaid, amount)
+}
+
+/// Records `amount` credited to some merchant's claimable balance.
+pub fn increase_merchant_liabilities(env: &Env, amount: i128) -> Result<(), Error> {
+    increase(env, DataKey::TotalMerchantBalances, amount)
+}
+
+/// Records `amount` debited from some merchant's claimable balance.
+pub fn decrease_merchant_liabilities(env: &Env, amount: i128) -> Result<(), Error> {
+    decrease(env, DataKey::TotalMerchantBalances, amount)
+}
+
+/// Compares tracked liabilities against the token balance actually held by the contract.
+pub fn get_solvency_report(env: &Env) -> Result<SolvencyReport, Error> {
+    let token_client = soroban_sdk::token::Client::new(env, &get_token(env)?);
+    let token_balance = token_client.balance(&env.current_contract_address());
+
+    let total_prepaid = get_total(env, &DataKey::TotalPrepaid);
+    let total_merchant_balances = get_total(env, &DataKey::TotalMerchantBalances);
+    let total_liabilities = safe_add(total_prepaid, total_merchant_balances)?;
+    let surplus = safe_sub(token_balance, total_liabilities)?;
+
+    Ok(SolvencyReport {
+        token_balance,
+        total_prepaid,
+        total_merchant_balances,
+        total_liabilities,
+        surplus,
+        is_solvent: surplus >= 0,
+    })
+}
@@ -2,8 +2,9 @@
 //!
 //! **PRs that only change admin or batch behavior should edit this file only.**
 
+use crate::accounting::get_solvency_report;
 use crate::charge_core::charge_one;
-use crate::types::{BatchChargeResult, Error, RecoveryEvent, RecoveryReason};
+use crate::types::{BatchChargeResult, ChargeOutcome, Error, RecoveryEvent, RecoveryReason};
 use soroban_sdk::{Address, Env, Symbol, Vec};
 
 pub fn do_init(env: &Env, token: Address, admin: Address, min_topup: i128) -> Result<(), Error> {
@@ -30,6 +31,14 @@ pub fn require_admin(env: &Env) -> Result<Address, Error> {
         .ok_or(Error::Unauthorized)
 }
 
+/// Returns the configured token (USDC) address.
+pub fn get_token(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotFound)
+}
+
 pub fn do_set_min_topup(env: &Env, admin: Address, min_topup: i128) -> Result<(), Error> {
     admin.require_auth();
     let stored = require_admin(env)?;
@@ -60,11 +69,10 @@ pub fn do_batch_charge(
 
     let mut results = Vec::new(env);
     for id in subscription_ids.iter() {
-        let r = charge_one(env, id, None);
-        let res = match &r {
-            Ok(()) => BatchChargeResult {
-                success: true,
-                error_code: 0,
+        let res = match charge_one(env, id, None) {
+            Ok(outcome) => BatchChargeResult {
+                success: outcome == ChargeOutcome::Charged,
+                error_code: outcome.to_code(),
             },
             Err(e) => BatchChargeResult {
                 success: false,
@@ -131,6 +139,13 @@ pub fn do_recover_stranded_funds(
         return Err(Error::InvalidRecoveryAmount);
     }
 
+    // Only funds above tracked liabilities may leave; subscriber and merchant
+    // balances are never recoverable.
+    let report = get_solvency_report(env)?;
+    if amount > report.surplus {
+        return Err(Error::ExceedsSurplus);
+    }
+
     let recovery_event = RecoveryEvent {
         admin: admin.clone(),
         recipient: recipient.clone(),
@@ -144,8 +159,8 @@ pub fn do_recover_stranded_funds(
         recovery_event,
     );
 
-    // TODO: Actual token transfer logic
-    // token_client.transfer(&env.current_contract_address(), &recipient, &amount);
+    let token_client = soroban_sdk::token::Client::new(env, &get_token(env)?);
+    token_client.transfer(&env.current_contract_address(), &recipient, &amount);
 
     Ok(())
 }
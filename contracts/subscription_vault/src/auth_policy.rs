@@ -0,0 +1,93 @@
+//! Who may pause, resume and cancel a merchant's subscriptions.
+//!
+//! **PRs that only change lifecycle authorization should edit this file only.**
+//!
+//! Each merchant may set a [`LifecycleAuthPolicy`] listing the parties allowed
+//! to perform each action. Without one, the subscriber and the merchant may do
+//! all three. Any other caller gets `Unauthorized`. `Party::Manager` refers to
+//! the delegated manager address named in the policy (e.g. a support desk).
+//! A policy may restrict pause and resume freely, but its cancel list must
+//! include the subscriber, so a subscriber can always stop their own billing.
+
+use crate::admin::require_admin;
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error, LifecycleAuthPolicy, Party, Subscription};
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+/// A lifecycle action governed by the policy.
+pub enum LifecycleAction {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+fn default_policy(env: &Env) -> LifecycleAuthPolicy {
+    let both = Vec::from_array(env, [Party::Subscriber, Party::Merchant]);
+    LifecycleAuthPolicy {
+        pause: both.clone(),
+        resume: both.clone(),
+        cancel: both,
+        manager: None,
+    }
+}
+
+pub fn do_set_lifecycle_auth_policy(
+    env: &Env,
+    merchant: Address,
+    policy: LifecycleAuthPolicy,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    if !policy.cancel.contains(Party::Subscriber) {
+        return Err(Error::InvalidAuthPolicy);
+    }
+    write_persistent(env, &DataKey::MerchantAuthPolicy(merchant.clone()), &policy);
+    env.events()
+        .publish((Symbol::new(env, "auth_policy_set"), merchant), policy);
+    Ok(())
+}
+
+/// The merchant's policy, or the default (subscriber and merchant) if unset.
+pub fn get_lifecycle_auth_policy(env: &Env, merchant: &Address) -> LifecycleAuthPolicy {
+    read_persistent(env, &DataKey::MerchantAuthPolicy(merchant.clone()))
+        .unwrap_or_else(|| default_policy(env))
+}
+
+fn acts_as(
+    env: &Env,
+    sub: &Subscription,
+    policy: &LifecycleAuthPolicy,
+    who: &Address,
+    party: &Party,
+) -> bool {
+    match party {
+        Party::Subscriber => *who == sub.subscriber,
+        Party::Merchant => *who == sub.merchant,
+        Party::Admin => require_admin(env).is_ok_and(|admin| admin == *who),
+        Party::Manager => policy.manager.as_ref() == Some(who),
+    }
+}
+
+/// Requires `authorizer`'s auth and checks the merchant's policy lets them
+/// perform `action` on `sub`.
+pub fn require_lifecycle_auth(
+    env: &Env,
+    sub: &Subscription,
+    authorizer: &Address,
+    action: LifecycleAction,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+    let policy = get_lifecycle_auth_policy(env, &sub.merchant);
+    let allowed = match action {
+        LifecycleAction::Pause => &policy.pause,
+        LifecycleAction::Resume => &policy.resume,
+        LifecycleAction::Cancel => &policy.cancel,
+    };
+    if allowed
+        .iter()
+        .any(|party| acts_as(env, sub, &policy, authorizer, &party))
+    {
+        Ok(())
+    } else {
+        Err(Error::Unauthorized)
+    }
+}
@@ -0,0 +1,66 @@
+//! Gregorian calendar arithmetic on UTC Unix timestamps, without `std` or a date crate.
+//!
+//! **PRs that only change date math should edit this file only.**
+//!
+//! Months are handled as a running index (`year * 12 + month - 1`) so adding
+//! months is plain integer addition. Day-of-month requests past the end of a
+//! month are clamped to its last day (e.g. the 31st bills on 30 April and on
+//! 28 or 29 February).
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Number of days in `month` (1–12) of `year`.
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a civil date (H. Hinnant's `days_from_civil`).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Civil date `(year, month, day)` of a day count since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Running month index of `timestamp`: `year * 12 + month - 1`.
+pub fn month_index(timestamp: u64) -> i64 {
+    let (year, month, _) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    year * 12 + month as i64 - 1
+}
+
+/// Timestamp of `day` (clamped to the month's length) in the month with index
+/// `month_index`, at `time_of_day` seconds past midnight. `None` if it falls
+/// before 1970 or outside `u64`.
+pub fn month_day_at(month_index: i64, day: u32, time_of_day: u64) -> Option<u64> {
+    let year = month_index.div_euclid(12);
+    let month = (month_index.rem_euclid(12) + 1) as u32;
+    let day = day.clamp(1, days_in_month(year, month));
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    days.checked_mul(SECONDS_PER_DAY)?.checked_add(time_of_day)
+}
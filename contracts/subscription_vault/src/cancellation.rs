@@ -0,0 +1,87 @@
+//! Cancellation at the end of the current billing period.
+//!
+//! **PRs that only change scheduled cancellation should edit this file only.**
+//!
+//! `schedule_cancellation` sets `cancel_at_period_end` instead of cancelling
+//! right away, so the subscriber keeps the period they already paid for. Once
+//! the next charge would fall due, the subscription is treated as `Cancelled`:
+//! the transition is applied lazily whenever it is loaded (by a charge attempt,
+//! a query or any other entrypoint) and persisted the next time it is saved.
+//! No further charges are made. Until then the cancellation can be undone.
+
+use crate::auth_policy::{require_lifecycle_auth, LifecycleAction};
+use crate::queries::get_subscription;
+use crate::schedule::next_charge_due;
+use crate::state_machine::{can_transition, validate_status_transition};
+use crate::storage::save_subscription;
+use crate::types::{Error, Subscription, SubscriptionStatus};
+use soroban_sdk::{Address, Env, Symbol};
+
+/// Applies a scheduled cancellation whose period has ended. Returns `true` if
+/// `sub` was moved to `Cancelled`; the caller decides whether to save it.
+pub fn apply_due_cancellation(sub: &mut Subscription, now: u64) -> bool {
+    if !sub.cancel_at_period_end
+        || !can_transition(&sub.status, &SubscriptionStatus::Cancelled)
+        || now < next_charge_due(sub).unwrap_or(u64::MAX)
+    {
+        return false;
+    }
+    sub.status = SubscriptionStatus::Cancelled;
+    sub.cancel_at_period_end = false;
+    true
+}
+
+/// Loads the subscription, checking `authorizer` may cancel it.
+fn authorized_subscription(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: &Address,
+) -> Result<Subscription, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    require_lifecycle_auth(env, &sub, authorizer, LifecycleAction::Cancel)?;
+    Ok(sub)
+}
+
+pub fn do_schedule_cancellation(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: Address,
+) -> Result<(), Error> {
+    let mut sub = authorized_subscription(env, subscription_id, &authorizer)?;
+    validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Ok(());
+    }
+
+    sub.cancel_at_period_end = true;
+    save_subscription(env, subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "cancel_scheduled"), subscription_id),
+        (authorizer, next_charge_due(&sub)?),
+    );
+    Ok(())
+}
+
+/// Withdraws a scheduled cancellation. Fails with `NotActive` once it has
+/// taken effect.
+pub fn do_undo_scheduled_cancellation(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: Address,
+) -> Result<(), Error> {
+    let mut sub = authorized_subscription(env, subscription_id, &authorizer)?;
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotActive);
+    }
+    if !sub.cancel_at_period_end {
+        return Ok(());
+    }
+
+    sub.cancel_at_period_end = false;
+    save_subscription(env, subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "cancel_undone"), subscription_id),
+        authorizer,
+    );
+    Ok(())
+}
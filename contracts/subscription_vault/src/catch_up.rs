@@ -0,0 +1,112 @@
+//! Catch-up charging: settling billing periods that elapsed without a charge.
+//!
+//! **PRs that only change how missed periods are handled should edit this file only.**
+//!
+//! `charge_subscription` bills only the period containing the current time, so
+//! periods missed while the billing engine was down are forgiven. Merchants can
+//! choose a [`CatchUpPolicy`] instead; `charge_subscription_catch_up` then
+//! charges the missed periods the policy allows, oldest first, followed by the
+//! current one. Each period is billed at its own price (trial, coupon and
+//! scheduled price changes apply per period). If the balance runs out part-way,
+//! the periods settled so far are kept and the rest stay outstanding. Periods
+//! past a fixed term (see [`crate::term`]) are never charged.
+
+use crate::charge_core::{amount_due, period_already_charged, settle_charge};
+use crate::dunning::{check_retry, record_failed_attempt};
+use crate::postpaid::with_pending_usage;
+use crate::pricing::apply_price_change;
+use crate::queries::get_subscription;
+use crate::schedule::{next_charge_due, period_at, period_start};
+use crate::storage::{read_persistent, write_persistent};
+use crate::term::{beyond_term, end_term};
+use crate::types::{CatchUpPolicy, DataKey, Error, SubscriptionStatus};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn do_set_catch_up_policy(
+    env: &Env,
+    merchant: Address,
+    policy: CatchUpPolicy,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    write_persistent(env, &DataKey::MerchantCatchUp(merchant.clone()), &policy);
+    env.events()
+        .publish((Symbol::new(env, "catch_up_policy_set"), merchant), policy);
+    Ok(())
+}
+
+/// The merchant's catch-up policy; `Forgive` unless set.
+pub fn get_catch_up_policy(env: &Env, merchant: &Address) -> CatchUpPolicy {
+    read_persistent(env, &DataKey::MerchantCatchUp(merchant.clone()))
+        .unwrap_or(CatchUpPolicy::Forgive)
+}
+
+/// Charges the outstanding periods allowed by the merchant's policy, in order,
+/// and returns how many were settled.
+///
+/// Fails like `charge_subscription` when nothing is due. Returns 0 when not
+/// even the first period can be paid (the failed attempt is recorded as by
+/// `charge_subscription`) or when it lies past the term (the subscription
+/// completes).
+pub fn charge_catch_up(env: &Env, subscription_id: u32) -> Result<u32, Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    let now = env.ledger().timestamp();
+    let due = next_charge_due(&sub)?;
+
+    if sub.status == SubscriptionStatus::InsufficientBalance {
+        if check_retry(env, subscription_id, &mut sub, due, now)?.is_some() {
+            return Ok(0);
+        }
+    } else if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+
+    let Some(current) = period_at(&sub, now) else {
+        return Err(Error::IntervalNotElapsed);
+    };
+    if period_already_charged(env, subscription_id, current) {
+        return Err(Error::Replay);
+    }
+    if now < due {
+        return Err(Error::IntervalNotElapsed);
+    }
+
+    let first_unpaid = period_at(&sub, due).unwrap_or(current);
+    let missed = current.saturating_sub(first_unpaid);
+    let allowed = match get_catch_up_policy(env, &sub.merchant) {
+        CatchUpPolicy::Forgive => 0,
+        CatchUpPolicy::All => missed,
+        CatchUpPolicy::UpTo(limit) => missed.min(limit.into()),
+    };
+
+    let mut settled: u32 = 0;
+    for period in (current - allowed)..=current {
+        let start = period_start(&sub, period)?;
+        if beyond_term(&sub, start) {
+            if settled == 0 {
+                end_term(env, subscription_id, &mut sub)?;
+                return Ok(0);
+            }
+            break;
+        }
+        apply_price_change(env, subscription_id, &mut sub, start);
+        let amount = amount_due(&sub, start);
+        if sub.prepaid_balance < with_pending_usage(&sub, amount)? {
+            if settled == 0 {
+                record_failed_attempt(env, subscription_id, &mut sub, due, now)?;
+                return Ok(0);
+            }
+            break;
+        }
+        // A past period is recorded as paid at its start, so the next due time
+        // is the period after it rather than the one after `now`.
+        let paid_at = if period == current { now } else { start };
+        settle_charge(env, subscription_id, &mut sub, amount, period, paid_at)?;
+        settled += 1;
+    }
+
+    env.events().publish(
+        (Symbol::new(env, "caught_up"), subscription_id),
+        (settled, allowed + 1),
+    );
+    Ok(settled)
+}
@@ -1,140 +1,276 @@
-//! Single charge logic (no auth). Used by charge_subscription and batch_charge.
+//! Single charge logic (no auth). Used by charge_subscription, batch_charge and catch-up.
 //!
 //! **PRs that only change how one subscription is charged should edit this file only.**
 //!
 //! # Replay protection and idempotency
 //!
 //! Charges are protected against replay by:
-//! - **Period-based key**: We record the last charged billing period index per subscription.
+//! - **Period-based key**: We record the last charged billing period number per subscription,
+//!   counted from the subscription's `billing_anchor` (see [`crate::schedule`]).
 //!   A charge for the same period is rejected with [`Error::Replay`].
 //! - **Optional idempotency key**: If the caller supplies an idempotency key (e.g. for retries),
 //!   we store one key per subscription. A second call with the same key returns `Ok(())` without
 //!   debiting again (idempotent success). Storage stays bounded (one key and one period per sub).
 
+use crate::accounting::{decrease_prepaid_liabilities, increase_prepaid_liabilities};
+use crate::coupons::{consume_discount_cycle, discounted_amount};
+use crate::dunning::{check_retry, clear_failed_attempts, record_failed_attempt};
+use crate::merchant::{credit_merchant_balance, debit_merchant_balance, get_merchant_balance};
+use crate::meters::require_meter;
+use crate::postpaid::{accrue_usage, with_pending_usage};
+use crate::pricing::apply_price_change;
 use crate::queries::get_subscription;
-use crate::safe_math::safe_sub_balance;
+use crate::refunds::{record_charge_at, record_period_charge, reduce_charge_at};
+use crate::safe_math::{safe_add_balance, safe_sub_balance};
+use crate::schedule::{next_charge_due, period_at, period_start};
 use crate::state_machine::validate_status_transition;
-use crate::types::{Error, SubscriptionChargedEvent, SubscriptionStatus};
-use soroban_sdk::{symbol_short, Env, Symbol};
+use crate::storage::{read_persistent, save_subscription, write_persistent};
+use crate::term::{beyond_term, emit_completed, end_term, record_cycle};
+use crate::trials::{emit_trial_converted, end_trial, trial_price};
+use crate::types::{
+    ChargeOutcome, DataKey, Error, LastCharge, Subscription, SubscriptionChargedEvent,
+    SubscriptionStatus,
+};
+use crate::usage_caps::{consume_usage_allowance, release_usage_allowance};
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
 
-const KEY_CHARGED_PERIOD: Symbol = symbol_short!("cp");
-const KEY_IDEM: Symbol = symbol_short!("idem");
-
-fn charged_period_key(subscription_id: u32) -> (Symbol, u32) {
-    (KEY_CHARGED_PERIOD, subscription_id)
+/// Amount billed for the period starting at `period_start`: the trial price while
+/// in trial, otherwise `amount` less any coupon discount that still has cycles left.
+pub fn amount_due(sub: &Subscription, period_start: u64) -> i128 {
+    trial_price(sub, period_start).unwrap_or_else(|| discounted_amount(sub, sub.amount))
 }
 
-fn idem_key(subscription_id: u32) -> (Symbol, u32) {
-    (KEY_IDEM, subscription_id)
+/// Advances trial and discount state after the period starting at `period_start`
+/// has been billed. Returns `true` if this charge converted a trial to paid.
+pub fn roll_forward(sub: &mut Subscription, period_start: u64) -> bool {
+    if trial_price(sub, period_start).is_some() {
+        return false;
+    }
+    consume_discount_cycle(sub);
+    end_trial(sub)
 }
 
 /// Performs a single interval-based charge with optional replay protection.
 ///
+/// If the balance cannot cover the charge, the failed attempt is recorded
+/// under the merchant's dunning policy and returned as a [`ChargeOutcome`].
+///
 /// # Idempotency
 ///
 /// - If `idempotency_key` is `Some(k)` and we already processed this subscription with key `k`,
-///   returns `Ok(())` without changing state (idempotent success).
-/// - Otherwise we derive a period from `now / interval_seconds`. If this period was already
+///   returns `Ok(ChargeOutcome::Charged)` without changing state (idempotent success).
+/// - Otherwise we bill the anchored period containing `now`. If this period was already
 ///   charged, returns `Err(Error::Replay)`.
 ///
 /// # Storage
 ///
-/// Bounded: one `u64` (last charged period) and optionally one idempotency key per subscription.
+/// Bounded per subscription: one `u64` (last charged period), optionally one
+/// idempotency key, and the refundable amounts of at most `REFUNDABLE_PERIODS`
+/// recent periods in a single entry.
 pub fn charge_one(
     env: &Env,
     subscription_id: u32,
     idempotency_key: Option<soroban_sdk::BytesN<32>>,
-) -> Result<(), Error> {
+) -> Result<ChargeOutcome, Error> {
     let mut sub = get_subscription(env, subscription_id)?;
+    let now = env.ledger().timestamp();
 
-    if sub.status != SubscriptionStatus::Active {
+    let due = next_charge_due(&sub)?;
+
+    // A failed charge may be retried under the merchant's dunning policy.
+    if sub.status == SubscriptionStatus::InsufficientBalance {
+        if let Some(outcome) = check_retry(env, subscription_id, &mut sub, due, now)? {
+            return Ok(outcome);
+        }
+    } else if sub.status != SubscriptionStatus::Active {
         return Err(Error::NotActive);
     }
 
-    let now = env.ledger().timestamp();
-    let period_index = now / sub.interval_seconds;
-
     // Idempotent return: same idempotency key already processed for this subscription
     if let Some(ref k) = idempotency_key {
-        if let Some(stored) = env
-            .storage()
-            .instance()
-            .get::<_, soroban_sdk::BytesN<32>>(&idem_key(subscription_id))
+        if let Some(stored) =
+            read_persistent::<soroban_sdk::BytesN<32>>(env, &DataKey::IdemKey(subscription_id))
         {
             if stored == *k {
-                return Ok(());
+                return Ok(ChargeOutcome::Charged);
             }
         }
     }
 
+    // Billing period containing `now`, counted from the anchor.
+    let Some(period) = period_at(&sub, now) else {
+        return Err(Error::IntervalNotElapsed);
+    };
+
     // Replay: already charged for this billing period (derived key)
-    if let Some(stored_period) = env
-        .storage()
-        .instance()
-        .get::<_, u64>(&charged_period_key(subscription_id))
-    {
-        if period_index <= stored_period {
-            return Err(Error::Replay);
-        }
+    if period_already_charged(env, subscription_id, period) {
+        return Err(Error::Replay);
     }
-
-    let next_allowed = sub
-        .last_payment_timestamp
-        .checked_add(sub.interval_seconds)
-        .ok_or(Error::Overflow)?;
-    if now < next_allowed {
+    if now < due {
         return Err(Error::IntervalNotElapsed);
     }
 
-    match safe_sub_balance(sub.prepaid_balance, sub.amount) {
-        Ok(new_balance) => {
-            sub.prepaid_balance = new_balance;
-            sub.last_payment_timestamp = now;
-            env.storage().instance().set(&subscription_id, &sub);
+    let start = period_start(&sub, period)?;
+    if beyond_term(&sub, start) {
+        end_term(env, subscription_id, &mut sub)?;
+        return Ok(ChargeOutcome::TermEnded);
+    }
 
-            // Record charged period and optional idempotency key (bounded storage)
-            env.storage()
-                .instance()
-                .set(&charged_period_key(subscription_id), &period_index);
-            if let Some(k) = idempotency_key {
-                env.storage().instance().set(&idem_key(subscription_id), &k);
-            }
+    // Switch to any announced price that is effective by the period start.
+    apply_price_change(env, subscription_id, &mut sub, start);
+    let amount = amount_due(&sub, start);
+
+    if sub.prepaid_balance < with_pending_usage(&sub, amount)? {
+        return record_failed_attempt(env, subscription_id, &mut sub, due, now);
+    }
+
+    settle_charge(env, subscription_id, &mut sub, amount, period, now)?;
+    // Optional idempotency key (bounded storage: one per subscription)
+    if let Some(k) = idempotency_key {
+        write_persistent(env, &DataKey::IdemKey(subscription_id), &k);
+    }
+    Ok(ChargeOutcome::Charged)
+}
+
+pub fn period_already_charged(env: &Env, subscription_id: u32, period: u64) -> bool {
+    read_persistent::<u64>(env, &DataKey::ChargedPeriod(subscription_id))
+        .is_some_and(|stored_period| period <= stored_period)
+}
+
+/// Debits `amount` for anchored billing `period` together with any pending
+/// postpaid usage, credits the merchant, records the charged period and emits
+/// `charged`. `paid_at` becomes the last payment time and must fall inside
+/// `period`. The caller has checked the balance covers the total (see
+/// [`with_pending_usage`]). Returns the total debited.
+pub fn settle_charge(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    amount: i128,
+    period: u64,
+    paid_at: u64,
+) -> Result<i128, Error> {
+    let start = period_start(sub, period)?;
+    let usage_amount = sub.pending_usage;
+    let total = with_pending_usage(sub, amount)?;
+    sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, total)?;
+    sub.total_charged = safe_add_balance(sub.total_charged, total)?;
+    sub.pending_usage = 0;
+    sub.last_payment_timestamp = paid_at;
+    clear_failed_attempts(sub)?;
+    let converted = roll_forward(sub, start);
+    let completed = record_cycle(sub)?;
+    save_subscription(env, subscription_id, sub);
+    if converted {
+        emit_trial_converted(env, subscription_id, sub);
+    }
+    if completed {
+        emit_completed(env, subscription_id, sub);
+    }
+    decrease_prepaid_liabilities(env, total)?;
+    credit_merchant_balance(env, &sub.merchant, total)?;
 
-            env.events().publish(
-                (symbol_short!("charged"),),
-                SubscriptionChargedEvent {
-                    subscription_id,
-                    merchant: sub.merchant.clone(),
-                    amount: sub.amount,
-                },
-            );
+    // Record charged period (bounded storage)
+    write_persistent(env, &DataKey::ChargedPeriod(subscription_id), &period);
+    record_period_charge(env, subscription_id, period, total)?;
+    write_persistent(
+        env,
+        &DataKey::LastCharge(subscription_id),
+        &LastCharge {
+            amount,
+            period_start: start,
+        },
+    );
 
-            Ok(())
+    env.events().publish(
+        (symbol_short!("charged"),),
+        SubscriptionChargedEvent {
+            subscription_id,
+            merchant: sub.merchant.clone(),
+            amount: total,
+            usage_amount,
+        },
+    );
+    Ok(total)
+}
+
+/// Called after a top-up of an `InsufficientBalance` subscription.
+///
+/// If a charge is overdue and the balance now covers it, charges it (without
+/// waiting for the dunning retry spacing) and reactivates the subscription. If
+/// nothing is overdue (e.g. usage drained the balance mid-period), reactivates
+/// it directly. Emits `reactivated` with the amount charged. If the balance
+/// still falls short, nothing changes and no failed attempt is recorded.
+pub fn reactivate_after_deposit(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.status != SubscriptionStatus::InsufficientBalance {
+        return Ok(());
+    }
+    let now = env.ledger().timestamp();
+    let overdue = if now >= next_charge_due(&sub)? {
+        period_at(&sub, now).filter(|p| !period_already_charged(env, subscription_id, *p))
+    } else {
+        None
+    };
+
+    let charged = if let Some(period) = overdue {
+        let start = period_start(&sub, period)?;
+        if beyond_term(&sub, start) {
+            return end_term(env, subscription_id, &mut sub);
         }
-        Err(_) => {
-            validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
-            sub.status = SubscriptionStatus::InsufficientBalance;
-            env.storage().instance().set(&subscription_id, &sub);
-            Err(Error::InsufficientBalance)
+        apply_price_change(env, subscription_id, &mut sub, start);
+        let amount = amount_due(&sub, start);
+        if sub.prepaid_balance < with_pending_usage(&sub, amount)? {
+            return Ok(());
         }
-    }
+        settle_charge(env, subscription_id, &mut sub, amount, period, now)?
+    } else {
+        clear_failed_attempts(&mut sub)?;
+        save_subscription(env, subscription_id, &sub);
+        0
+    };
+
+    env.events().publish(
+        (Symbol::new(env, "reactivated"), subscription_id),
+        (sub.subscriber, charged),
+    );
+    Ok(())
 }
 
 /// Debit a metered `usage_amount` from a subscription's prepaid balance.
 ///
 /// Shared safety checks:
 /// * Subscription must exist (`NotFound`).
+/// * `meter` must authorize and be registered for the subscription's merchant
+///   or plan (`Unauthorized`).
 /// * Subscription must be `Active` (`NotActive`).
 /// * `usage_enabled` must be `true` (`UsageNotEnabled`).
 /// * `usage_amount` must be positive (`InvalidAmount`).
 /// * `prepaid_balance >= usage_amount` (`InsufficientPrepaidBalance`).
+/// * Within the subscriber's usage caps, if set (`UsageCapExceeded`).
 ///
-/// On success the prepaid balance is reduced.  If the balance reaches zero
+/// On success the prepaid balance is reduced and the merchant's internal
+/// balance is credited with the same amount.  If the balance reaches zero
 /// the subscription transitions to `InsufficientBalance`, blocking further
 /// charges until the subscriber tops up.
-pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
-    let mut sub = get_subscription(env, subscription_id)?;
+pub fn charge_usage_one(
+    env: &Env,
+    subscription_id: u32,
+    meter: &Address,
+    usage_amount: i128,
+) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    require_meter(env, &sub, meter)?;
+    debit_usage(env, subscription_id, sub, usage_amount)
+}
 
+/// The usage debit behind `charge_usage_one`, once the reporter is authorized.
+pub fn debit_usage(
+    env: &Env,
+    subscription_id: u32,
+    mut sub: Subscription,
+    usage_amount: i128,
+) -> Result<(), Error> {
     if sub.status != SubscriptionStatus::Active {
         return Err(Error::NotActive);
     }
@@ -147,14 +283,21 @@ pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) ->
         return Err(Error::InvalidAmount);
     }
 
+    if sub.usage_in_arrears {
+        consume_usage_allowance(env, subscription_id, &sub, usage_amount)?;
+        return accrue_usage(env, subscription_id, &mut sub, usage_amount);
+    }
+
     if sub.prepaid_balance < usage_amount {
         return Err(Error::InsufficientPrepaidBalance);
     }
+    consume_usage_allowance(env, subscription_id, &sub, usage_amount)?;
 
     sub.prepaid_balance = sub
         .prepaid_balance
         .checked_sub(usage_amount)
         .ok_or(Error::Overflow)?;
+    sub.total_charged = safe_add_balance(sub.total_charged, usage_amount)?;
 
     // If the vault is now empty, transition to InsufficientBalance so no
     // further charges (interval or usage) can proceed until top-up.
@@ -163,6 +306,48 @@ pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) ->
         sub.status = SubscriptionStatus::InsufficientBalance;
     }
 
-    env.storage().instance().set(&subscription_id, &sub);
-    Ok(())
+    save_subscription(env, subscription_id, &sub);
+    decrease_prepaid_liabilities(env, usage_amount)?;
+    credit_merchant_balance(env, &sub.merchant, usage_amount)?;
+    record_charge_at(
+        env,
+        subscription_id,
+        &sub,
+        env.ledger().timestamp(),
+        usage_amount,
+    )
+}
+
+/// Gives back up to `amount` of usage charged in the current period, e.g. when
+/// volume pricing moves the period's total into a cheaper tier. Postpaid usage
+/// is taken off `pending_usage`; usage already paid moves from the merchant's
+/// balance back into `prepaid_balance`, limited by what the merchant has not
+/// withdrawn. Returns the amount credited.
+pub fn credit_usage(
+    env: &Env,
+    subscription_id: u32,
+    mut sub: Subscription,
+    amount: i128,
+) -> Result<i128, Error> {
+    let now = env.ledger().timestamp();
+    let credited = if sub.usage_in_arrears {
+        let credited = amount.min(sub.pending_usage);
+        sub.pending_usage = safe_sub_balance(sub.pending_usage, credited)?;
+        credited
+    } else {
+        let credited = amount.min(get_merchant_balance(env, &sub.merchant));
+        if credited > 0 {
+            debit_merchant_balance(env, &sub.merchant, credited)?;
+            sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, credited)?;
+            sub.total_charged = safe_sub_balance(sub.total_charged, credited)?;
+            increase_prepaid_liabilities(env, credited)?;
+            reduce_charge_at(env, subscription_id, &sub, now, credited)?;
+        }
+        credited
+    };
+    if credited > 0 {
+        release_usage_allowance(env, subscription_id, &sub, credited)?;
+        save_subscription(env, subscription_id, &sub);
+    }
+    Ok(credited)
 }
@@ -0,0 +1,147 @@
+//! Merchant coupon registry: create, deactivate, redeem at subscription creation.
+//!
+//! **PRs that only change coupons or discount math should edit this file only.**
+//!
+//! A coupon is identified by `(merchant, code)`. Redeeming one at subscription
+//! creation copies its discount onto the subscription, where it applies to the
+//! next `cycles` full-price charges (trial charges do not use up cycles).
+
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{Coupon, DataKey, Discount, Error, Subscription};
+use soroban_sdk::{Address, Env, Symbol};
+
+/// Basis points in 100%.
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+pub fn get_coupon(env: &Env, merchant: &Address, code: &Symbol) -> Result<Coupon, Error> {
+    read_persistent(env, &DataKey::Coupon(merchant.clone(), code.clone())).ok_or(Error::NotFound)
+}
+
+fn save_coupon(env: &Env, coupon: &Coupon) {
+    write_persistent(
+        env,
+        &DataKey::Coupon(coupon.merchant.clone(), coupon.code.clone()),
+        coupon,
+    );
+}
+
+fn validate_discount(discount: &Discount) -> Result<(), Error> {
+    let valid = match discount {
+        Discount::None => false,
+        Discount::PercentBps(bps) => *bps > 0 && *bps <= BPS_DENOMINATOR,
+        Discount::Fixed(amount) => *amount > 0,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidAmount)
+    }
+}
+
+pub fn do_create_coupon(
+    env: &Env,
+    merchant: Address,
+    code: Symbol,
+    discount: Discount,
+    cycles: u32,
+    max_redemptions: u32,
+    expires_at: Option<u64>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    validate_discount(&discount)?;
+    if cycles == 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if env
+        .storage()
+        .persistent()
+        .has(&DataKey::Coupon(merchant.clone(), code.clone()))
+    {
+        return Err(Error::CouponExists);
+    }
+
+    let coupon = Coupon {
+        merchant: merchant.clone(),
+        code: code.clone(),
+        discount,
+        cycles,
+        max_redemptions,
+        redemptions: 0,
+        expires_at,
+        active: true,
+    };
+    save_coupon(env, &coupon);
+    env.events()
+        .publish((Symbol::new(env, "coupon_created"), merchant), code);
+    Ok(())
+}
+
+/// Stops new redemptions. Subscriptions that already redeemed the coupon keep their discount.
+pub fn do_deactivate_coupon(env: &Env, merchant: Address, code: Symbol) -> Result<(), Error> {
+    merchant.require_auth();
+    let mut coupon = get_coupon(env, &merchant, &code)?;
+    coupon.active = false;
+    save_coupon(env, &coupon);
+    env.events()
+        .publish((Symbol::new(env, "coupon_deactivated"), merchant), code);
+    Ok(())
+}
+
+/// Redeems `code` against `merchant`'s registry for `subscriber` and returns the
+/// discount and the number of cycles it covers.
+pub fn redeem_coupon(
+    env: &Env,
+    merchant: &Address,
+    subscriber: &Address,
+    code: Symbol,
+) -> Result<(Discount, u32), Error> {
+    let mut coupon = get_coupon(env, merchant, &code)?;
+    if !coupon.active {
+        return Err(Error::CouponUnavailable);
+    }
+    if let Some(expires_at) = coupon.expires_at {
+        if env.ledger().timestamp() >= expires_at {
+            return Err(Error::CouponUnavailable);
+        }
+    }
+    if coupon.max_redemptions > 0 && coupon.redemptions >= coupon.max_redemptions {
+        return Err(Error::CouponExhausted);
+    }
+    coupon.redemptions += 1;
+    save_coupon(env, &coupon);
+    env.events().publish(
+        (Symbol::new(env, "coupon_redeemed"), merchant.clone()),
+        (code, subscriber.clone()),
+    );
+    Ok((coupon.discount, coupon.cycles))
+}
+
+/// Applies `discount` to `amount`, never going below zero. Percentages round
+/// the discount down so the merchant never receives less than the exact figure.
+pub fn apply_discount(discount: &Discount, amount: i128) -> i128 {
+    let off = match discount {
+        Discount::None => 0,
+        Discount::PercentBps(bps) => amount.saturating_mul(*bps as i128) / BPS_DENOMINATOR as i128,
+        Discount::Fixed(fixed) => *fixed,
+    };
+    amount.saturating_sub(off).max(0)
+}
+
+/// `amount` less the subscription's running coupon discount, if any cycles remain.
+pub fn discounted_amount(sub: &Subscription, amount: i128) -> i128 {
+    if sub.discount_cycles_left == 0 {
+        return amount;
+    }
+    apply_discount(&sub.discount, amount)
+}
+
+/// Uses up one discounted cycle; clears the discount once none remain.
+pub fn consume_discount_cycle(sub: &mut Subscription) {
+    if sub.discount_cycles_left == 0 {
+        return;
+    }
+    sub.discount_cycles_left -= 1;
+    if sub.discount_cycles_left == 0 {
+        sub.discount = Discount::None;
+    }
+}
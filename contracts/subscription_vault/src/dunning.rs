@@ -0,0 +1,142 @@
+//! Dunning: what happens after an interval charge fails for insufficient balance.
+//!
+//! **PRs that only change retry or grace-period behaviour should edit this file only.**
+//!
+//! A [`DunningPolicy`] can be set globally by admin and overridden per merchant.
+//! Without a policy a failed subscription simply stays `InsufficientBalance`
+//! until the subscriber tops up (which settles the overdue charge). With a policy:
+//! - `charge_subscription` / `batch_charge` may retry the failed charge while the
+//!   subscription is `InsufficientBalance`, at most once per `retry_interval_seconds`;
+//! - every failed attempt increments `failed_attempts` on the subscription;
+//! - once the original attempt and `max_retries` retries have failed, or the grace period
+//!   (counted from when the charge was due) has elapsed, the subscription is
+//!   cancelled and `dunning_cancelled` is emitted.
+//!
+//! A failed attempt is reported as a [`ChargeOutcome`], not an error, so the
+//! recorded attempt and any cancellation persist whichever entrypoint charged.
+
+use crate::admin::require_admin;
+use crate::postpaid::settle_pending_usage;
+use crate::state_machine::validate_status_transition;
+use crate::storage::{read_persistent, save_subscription, write_persistent};
+use crate::types::{
+    ChargeOutcome, DataKey, DunningPolicy, Error, Subscription, SubscriptionStatus,
+};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn do_set_dunning_policy(
+    env: &Env,
+    admin: Address,
+    policy: DunningPolicy,
+) -> Result<(), Error> {
+    admin.require_auth();
+    if admin != require_admin(env)? {
+        return Err(Error::Unauthorized);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "dunning"), &policy);
+    env.events()
+        .publish((Symbol::new(env, "dunning_policy_set"),), policy);
+    Ok(())
+}
+
+pub fn do_set_merchant_dunning_policy(
+    env: &Env,
+    merchant: Address,
+    policy: DunningPolicy,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    write_persistent(env, &DataKey::MerchantDunning(merchant.clone()), &policy);
+    env.events()
+        .publish((Symbol::new(env, "dunning_policy_set"), merchant), policy);
+    Ok(())
+}
+
+/// The merchant's own policy if set, otherwise the global one.
+pub fn get_dunning_policy(env: &Env, merchant: &Address) -> Option<DunningPolicy> {
+    read_persistent(env, &DataKey::MerchantDunning(merchant.clone()))
+        .or_else(|| env.storage().instance().get(&Symbol::new(env, "dunning")))
+}
+
+fn grace_expired(policy: &DunningPolicy, due: u64, now: u64) -> bool {
+    now >= due.saturating_add(policy.grace_period_seconds)
+}
+
+fn cancel_for_dunning(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+) -> Result<(), Error> {
+    validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+    sub.status = SubscriptionStatus::Cancelled;
+    settle_pending_usage(env, subscription_id, sub)?;
+    save_subscription(env, subscription_id, sub);
+    env.events().publish(
+        (Symbol::new(env, "dunning_cancelled"), subscription_id),
+        (sub.subscriber.clone(), sub.failed_attempts),
+    );
+    Ok(())
+}
+
+/// Checks that a retry of a failed charge (due at `due`) may run now.
+///
+/// Fails with `NotActive` if there is no policy and `RetryNotDue` if the retry
+/// spacing has not elapsed. If the grace period is already over, cancels the
+/// subscription and returns `Some(DunningCancelled)` instead of a retry.
+pub fn check_retry(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    due: u64,
+    now: u64,
+) -> Result<Option<ChargeOutcome>, Error> {
+    let policy = get_dunning_policy(env, &sub.merchant).ok_or(Error::NotActive)?;
+    if grace_expired(&policy, due, now) {
+        cancel_for_dunning(env, subscription_id, sub)?;
+        return Ok(Some(ChargeOutcome::DunningCancelled));
+    }
+    if now
+        < sub
+            .last_failed_at
+            .saturating_add(policy.retry_interval_seconds)
+    {
+        return Err(Error::RetryNotDue);
+    }
+    Ok(None)
+}
+
+/// Records a failed charge attempt: moves the subscription to
+/// `InsufficientBalance`, or cancels it when the policy is exhausted.
+pub fn record_failed_attempt(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    due: u64,
+    now: u64,
+) -> Result<ChargeOutcome, Error> {
+    sub.failed_attempts = sub.failed_attempts.saturating_add(1);
+    sub.last_failed_at = now;
+
+    if let Some(policy) = get_dunning_policy(env, &sub.merchant) {
+        // The first failure is the original attempt, not a retry.
+        if sub.failed_attempts > policy.max_retries || grace_expired(&policy, due, now) {
+            cancel_for_dunning(env, subscription_id, sub)?;
+            return Ok(ChargeOutcome::DunningCancelled);
+        }
+    }
+
+    validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
+    sub.status = SubscriptionStatus::InsufficientBalance;
+    save_subscription(env, subscription_id, sub);
+    Ok(ChargeOutcome::InsufficientBalance)
+}
+
+/// Clears dunning state after a successful charge.
+pub fn clear_failed_attempts(sub: &mut Subscription) -> Result<(), Error> {
+    validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
+    sub.status = SubscriptionStatus::Active;
+    sub.failed_attempts = 0;
+    sub.last_failed_at = 0;
+    Ok(())
+}
@@ -1,13 +1,35 @@
 #![no_std]
 
 // ── Modules ──────────────────────────────────────────────────────────────────
+mod accounting;
 mod admin;
+mod auth_policy;
+mod calendar;
+mod cancellation;
+mod catch_up;
 mod charge_core;
+mod coupons;
+mod dunning;
 mod merchant;
+mod meters;
+mod oneoff;
+mod pause;
+mod plans;
+mod postpaid;
+mod pricing;
+mod proration;
 mod queries;
+mod rate_cards;
+mod receipts;
+mod refunds;
+mod schedule;
 mod state_machine;
+mod storage;
 mod subscription;
+mod term;
+mod trials;
 pub mod types;
+mod usage_caps;
 
 mod safe_math;
 
@@ -16,7 +38,7 @@ pub use state_machine::{can_transition, get_allowed_transitions, validate_status
 pub use types::*;
 
 pub use queries::compute_next_charge_info;
-use soroban_sdk::{contract, contractimpl, Address, Env, Vec};
+use soroban_sdk::{contract, contractimpl, Address, BytesN, Env, String, Symbol, Vec};
 
 // ── Contract ─────────────────────────────────────────────────────────────────
 
@@ -63,6 +85,9 @@ impl SubscriptionVault {
     /// Tightly-scoped mechanism for recovering funds that have become
     /// inaccessible through normal operations. Each recovery emits a
     /// `RecoveryEvent` with full audit details.
+    ///
+    /// `amount` is capped at the surplus reported by [`Self::get_solvency_report`];
+    /// subscriber prepaid balances and merchant balances can never be recovered.
     pub fn recover_stranded_funds(
         env: Env,
         admin: Address,
@@ -73,6 +98,27 @@ impl SubscriptionVault {
         admin::do_recover_stranded_funds(&env, admin, recipient, amount, reason)
     }
 
+    /// Set the minimum notice (seconds) merchants must give before a price change.
+    /// Only callable by admin. Defaults to 30 days.
+    pub fn set_price_change_notice(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        pricing::do_set_price_change_notice(&env, admin, seconds)
+    }
+
+    /// Get the minimum price change notice period in seconds.
+    pub fn get_price_change_notice(env: Env) -> u64 {
+        pricing::get_price_change_notice(&env)
+    }
+
+    /// Set the global dunning policy (grace period, retries, retry spacing).
+    /// Merchants may override it. Only callable by admin.
+    pub fn set_dunning_policy(
+        env: Env,
+        admin: Address,
+        policy: DunningPolicy,
+    ) -> Result<(), Error> {
+        dunning::do_set_dunning_policy(&env, admin, policy)
+    }
+
     /// Charge a batch of subscriptions in one transaction. Admin only.
     ///
     /// Returns a per-subscription result vector so callers can identify
@@ -86,7 +132,17 @@ impl SubscriptionVault {
 
     // ── Subscription lifecycle ───────────────────────────────────────────
 
-    /// Create a new subscription. Caller deposits initial USDC; contract stores agreement.
+    /// Create a custom subscription with explicit terms.
+    ///
+    /// Requires auth from **both** subscriber and merchant so the price is agreed
+    /// by the party receiving it. Subscribers joining a published plan should use
+    /// [`Self::create_subscription_from_plan`].
+    ///
+    /// **Breaking change:** this entrypoint used to require the subscriber's auth
+    /// only. Callers must now also obtain the merchant's signature, or move to
+    /// plans. `interval_seconds` of 0 is rejected with `InvalidInterval`.
+    ///
+    /// `coupon` optionally redeems one of the merchant's coupon codes.
     pub fn create_subscription(
         env: Env,
         subscriber: Address,
@@ -94,6 +150,7 @@ impl SubscriptionVault {
         amount: i128,
         interval_seconds: u64,
         usage_enabled: bool,
+        coupon: Option<Symbol>,
     ) -> Result<u32, Error> {
         subscription::do_create_subscription(
             &env,
@@ -102,12 +159,109 @@ impl SubscriptionVault {
             amount,
             interval_seconds,
             usage_enabled,
+            coupon,
+        )
+    }
+
+    /// Create a custom fixed-term subscription, e.g. "12 monthly payments".
+    ///
+    /// It moves to the terminal `Completed` status (emitting `completed`) after
+    /// `max_cycles` interval charges, or once the next period would start at or
+    /// after `ends_at`; the subscriber can then withdraw the remaining balance.
+    /// Auth: subscriber and merchant.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_fixed_term_subscription(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        max_cycles: Option<u32>,
+        ends_at: Option<u64>,
+    ) -> Result<u32, Error> {
+        subscription::do_create_fixed_term_subscription(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            max_cycles,
+            ends_at,
+        )
+    }
+
+    /// Create a custom subscription billed on calendar dates instead of a fixed
+    /// interval, e.g. `MonthlyOnDay(15)`. Days past the end of a month bill on
+    /// its last day. The first charge falls due on the first billing date after
+    /// now. Auth: subscriber and merchant.
+    pub fn create_calendar_subscription(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        cadence: BillingCadence,
+        usage_enabled: bool,
+        coupon: Option<Symbol>,
+    ) -> Result<u32, Error> {
+        subscription::do_create_calendar_subscription(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            cadence,
+            usage_enabled,
+            coupon,
+        )
+    }
+
+    /// Create a custom subscription that starts with a trial.
+    ///
+    /// Periods are billed in advance. A free trial (`None`) charges first when it
+    /// ends; a discounted trial bills `trial_amount` for every period that starts
+    /// before the trial ends. The first full-price charge emits `trial_converted`.
+    /// Auth: subscriber and merchant.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_subscription_with_trial(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        trial_seconds: u64,
+        trial_amount: Option<i128>,
+    ) -> Result<u32, Error> {
+        subscription::do_create_subscription_with_trial(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            trial_seconds,
+            trial_amount,
         )
     }
 
+    /// Subscribe to a merchant's plan. Terms (including any trial) are copied
+    /// from the plan. `coupon` optionally redeems one of the merchant's coupon
+    /// codes. Auth: subscriber.
+    pub fn create_subscription_from_plan(
+        env: Env,
+        subscriber: Address,
+        plan_id: u32,
+        coupon: Option<Symbol>,
+    ) -> Result<u32, Error> {
+        subscription::do_create_subscription_from_plan(&env, subscriber, plan_id, coupon)
+    }
+
     /// Subscriber deposits more USDC into their prepaid vault.
     ///
-    /// Rejects deposits below the configured minimum threshold.
+    /// Rejects deposits below the configured minimum threshold. Topping up an
+    /// `InsufficientBalance` subscription charges the overdue period if the new
+    /// balance covers it and reactivates the subscription (`reactivated` event).
     pub fn deposit_funds(
         env: Env,
         subscription_id: u32,
@@ -117,6 +271,15 @@ impl SubscriptionVault {
         subscription::do_deposit_funds(&env, subscription_id, subscriber, amount)
     }
 
+    /// Move an active subscription to another of the same merchant's plans mid-cycle.
+    ///
+    /// The unused part of the current period is credited, the new plan's price is
+    /// charged for a period starting now, and the net is settled against the
+    /// prepaid balance. Emits `plan_changed` with the breakdown. Auth: subscriber.
+    pub fn change_plan(env: Env, subscription_id: u32, new_plan_id: u32) -> Result<(), Error> {
+        proration::do_change_plan(&env, subscription_id, new_plan_id)
+    }
+
     /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
     /// Transitions to the terminal `Cancelled` state.
     pub fn cancel_subscription(
@@ -127,7 +290,32 @@ impl SubscriptionVault {
         subscription::do_cancel_subscription(&env, subscription_id, authorizer)
     }
 
-    /// Subscriber withdraws their remaining prepaid_balance after cancellation.
+    /// Cancel at the end of the current paid period instead of immediately.
+    ///
+    /// No further charges are made; once the next charge would fall due the
+    /// subscription reads as `Cancelled` and the remaining balance can be
+    /// withdrawn. Emits `cancel_scheduled` with the effective time.
+    /// Auth: subscriber or merchant.
+    pub fn schedule_cancellation(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        cancellation::do_schedule_cancellation(&env, subscription_id, authorizer)
+    }
+
+    /// Withdraw a scheduled cancellation before the period ends.
+    /// Auth: subscriber or merchant.
+    pub fn undo_scheduled_cancellation(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        cancellation::do_undo_scheduled_cancellation(&env, subscription_id, authorizer)
+    }
+
+    /// Subscriber withdraws their remaining prepaid_balance after cancellation
+    /// or completion of a fixed term.
     pub fn withdraw_subscriber_funds(
         env: Env,
         subscription_id: u32,
@@ -137,6 +325,9 @@ impl SubscriptionVault {
     }
 
     /// Pause subscription (no charges until resumed). Allowed from Active.
+    ///
+    /// If the merchant caps pause length, the pause ends automatically after
+    /// the maximum.
     pub fn pause_subscription(
         env: Env,
         subscription_id: u32,
@@ -145,7 +336,23 @@ impl SubscriptionVault {
         subscription::do_pause_subscription(&env, subscription_id, authorizer)
     }
 
+    /// Pause subscription until `resume_at`, when it resumes by itself.
+    ///
+    /// `resume_at` must be in the future and within the merchant's maximum
+    /// pause length (`InvalidPause` otherwise).
+    pub fn pause_subscription_until(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+        resume_at: u64,
+    ) -> Result<(), Error> {
+        pause::do_pause_until(&env, subscription_id, authorizer, Some(resume_at))
+    }
+
     /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
+    ///
+    /// Resuming from a pause moves the billing clock forward by the time spent
+    /// paused, so the days already paid for are not lost.
     pub fn resume_subscription(
         env: Env,
         subscription_id: u32,
@@ -154,15 +361,37 @@ impl SubscriptionVault {
         subscription::do_resume_subscription(&env, subscription_id, authorizer)
     }
 
+    /// Extend the storage TTL of a subscription and its charge metadata.
+    ///
+    /// Anyone may call this (and pay the rent) to keep a long-idle subscription
+    /// from being archived.
+    pub fn extend_subscription_ttl(env: Env, subscription_id: u32) -> Result<(), Error> {
+        storage::extend_subscription_ttl(&env, subscription_id)
+    }
+
     // ── Charging ─────────────────────────────────────────────────────────
 
     /// Billing engine calls this to charge one interval.
     ///
-    /// Enforces strict interval timing and replay protection.
-    pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<(), Error> {
+    /// Enforces strict interval timing and replay protection. Bills only the
+    /// current period; see [`Self::charge_subscription_catch_up`] for missed ones.
+    ///
+    /// A balance that cannot cover the charge is not an error: the failed
+    /// attempt is recorded under the merchant's dunning policy and returned as
+    /// [`ChargeOutcome::InsufficientBalance`] or [`ChargeOutcome::DunningCancelled`].
+    /// A charge past a fixed term completes the subscription and returns
+    /// [`ChargeOutcome::TermEnded`].
+    pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<ChargeOutcome, Error> {
         charge_core::charge_one(&env, subscription_id, None)
     }
 
+    /// Charge the current period plus any missed periods the merchant's
+    /// catch-up policy allows, oldest first. Stops at the first period the
+    /// balance cannot cover and returns the number of periods settled.
+    pub fn charge_subscription_catch_up(env: Env, subscription_id: u32) -> Result<u32, Error> {
+        catch_up::charge_catch_up(&env, subscription_id)
+    }
+
     /// Charge a metered usage amount against the subscription's prepaid balance.
     ///
     /// Designed for integration with an **off-chain usage metering service**:
@@ -171,6 +400,9 @@ impl SubscriptionVault {
     ///
     /// # Requirements
     ///
+    /// * `meter` must authorize the call and be registered by the merchant
+    ///   (see [`Self::register_meter`]) for all their subscriptions or for the
+    ///   subscription's plan.
     /// * The subscription must be `Active`.
     /// * `usage_enabled` must be `true` on the subscription.
     /// * `usage_amount` must be positive (`> 0`).
@@ -178,7 +410,8 @@ impl SubscriptionVault {
     ///
     /// # Behaviour
     ///
-    /// On success, `prepaid_balance` is reduced by `usage_amount`.  If the
+    /// On success, `prepaid_balance` is reduced by `usage_amount` and the
+    /// merchant's internal balance is credited with it.  If the
     /// debit drains the balance to zero the subscription transitions to
     /// `InsufficientBalance` status, signalling that no further charges
     /// (interval or usage) can proceed until the subscriber tops up.
@@ -188,21 +421,442 @@ impl SubscriptionVault {
     /// | Variant | Reason |
     /// |---------|--------|
     /// | `NotFound` | Subscription ID does not exist. |
+    /// | `Unauthorized` | `meter` is not a registered meter. |
     /// | `NotActive` | Subscription is not `Active`. |
     /// | `UsageNotEnabled` | `usage_enabled` is `false`. |
     /// | `InvalidAmount` | `usage_amount` is zero or negative. |
     /// | `InsufficientPrepaidBalance` | Prepaid balance cannot cover the debit. |
-    pub fn charge_usage(env: Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
-        charge_core::charge_usage_one(&env, subscription_id, usage_amount)
+    /// | `UsageCapExceeded` | Debit exceeds the subscriber's usage caps. |
+    pub fn charge_usage(
+        env: Env,
+        subscription_id: u32,
+        meter: Address,
+        usage_amount: i128,
+    ) -> Result<(), Error> {
+        charge_core::charge_usage_one(&env, subscription_id, &meter, usage_amount)
+    }
+
+    /// Merchant registers a usage meter for all their subscriptions
+    /// (`plan_id = None`) or for the subscriptions of one of their plans.
+    pub fn register_meter(
+        env: Env,
+        merchant: Address,
+        meter: Address,
+        plan_id: Option<u32>,
+    ) -> Result<(), Error> {
+        meters::do_register_meter(&env, merchant, meter, plan_id)
+    }
+
+    /// Merchant revokes a usage meter. Fails with `NotFound` if not registered.
+    pub fn revoke_meter(
+        env: Env,
+        merchant: Address,
+        meter: Address,
+        plan_id: Option<u32>,
+    ) -> Result<(), Error> {
+        meters::do_revoke_meter(&env, merchant, meter, plan_id)
+    }
+
+    /// Merchant replaces a registered usage meter with a new one.
+    pub fn rotate_meter(
+        env: Env,
+        merchant: Address,
+        old_meter: Address,
+        new_meter: Address,
+        plan_id: Option<u32>,
+    ) -> Result<(), Error> {
+        meters::do_rotate_meter(&env, merchant, old_meter, new_meter, plan_id)
+    }
+
+    /// Whether `meter` is registered for the merchant (`plan_id = None`) or plan.
+    pub fn is_meter_registered(
+        env: Env,
+        merchant: Address,
+        meter: Address,
+        plan_id: Option<u32>,
+    ) -> bool {
+        meters::is_meter_registered(&env, &merchant, &meter, plan_id)
+    }
+
+    /// Merchant attaches a usage rate card (unit price, graduated or volume
+    /// tiers, included units per period) to one of their plans.
+    pub fn set_plan_rate_card(
+        env: Env,
+        merchant: Address,
+        plan_id: u32,
+        card: RateCard,
+    ) -> Result<(), Error> {
+        rate_cards::do_set_plan_rate_card(&env, merchant, plan_id, card)
+    }
+
+    /// The plan's usage rate card, if any.
+    pub fn get_plan_rate_card(env: Env, plan_id: u32) -> Option<RateCard> {
+        rate_cards::get_plan_rate_card(&env, plan_id)
+    }
+
+    /// A registered meter reports `units` of usage. The contract prices them
+    /// with the plan's rate card against the period-to-date total and debits
+    /// the result like [`Self::charge_usage`]. Returns the amount charged;
+    /// negative when volume pricing drops the period into a cheaper tier and
+    /// the difference is credited back.
+    pub fn report_usage_units(
+        env: Env,
+        subscription_id: u32,
+        meter: Address,
+        units: u64,
+    ) -> Result<i128, Error> {
+        rate_cards::do_report_usage_units(&env, subscription_id, meter, units)
+    }
+
+    /// Units reported and amount billed for them in the current billing period,
+    /// with the rate card pricing the period; `None` before the period's first report.
+    pub fn get_unit_usage(env: Env, subscription_id: u32) -> Result<Option<UnitUsage>, Error> {
+        rate_cards::get_unit_usage(&env, subscription_id)
+    }
+
+    /// Subscriber and merchant switch usage billing to arrears: usage accrues
+    /// into `pending_usage` and is settled with the next interval charge.
+    pub fn set_usage_in_arrears(
+        env: Env,
+        subscription_id: u32,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        postpaid::do_set_usage_in_arrears(&env, subscription_id, enabled)
+    }
+
+    /// Subscriber caps usage spend at `per_period` per billing period and,
+    /// optionally, `per_call` per usage charge.
+    pub fn set_usage_caps(
+        env: Env,
+        subscription_id: u32,
+        per_period: i128,
+        per_call: Option<i128>,
+    ) -> Result<(), Error> {
+        usage_caps::do_set_usage_caps(&env, subscription_id, per_period, per_call)
+    }
+
+    /// Subscriber lifts their usage caps.
+    pub fn remove_usage_caps(env: Env, subscription_id: u32) -> Result<(), Error> {
+        usage_caps::do_remove_usage_caps(&env, subscription_id)
+    }
+
+    /// The subscriber's usage caps, if any.
+    pub fn get_usage_caps(env: Env, subscription_id: u32) -> Option<UsageCaps> {
+        usage_caps::get_usage_caps(&env, subscription_id)
+    }
+
+    /// Usage that can still be charged in the current billing period (`None`
+    /// when uncapped).
+    pub fn get_usage_allowance(env: Env, subscription_id: u32) -> Result<Option<i128>, Error> {
+        usage_caps::get_usage_allowance(&env, subscription_id)
+    }
+
+    /// Submit a usage receipt signed by a registered meter key. Anyone may
+    /// relay it; the debit is the same as [`Self::charge_usage`]. Reused
+    /// nonces fail with `Replay`.
+    pub fn submit_usage_receipt(
+        env: Env,
+        receipt: UsageReceipt,
+        public_key: BytesN<32>,
+        signature: BytesN<64>,
+    ) -> Result<(), Error> {
+        receipts::do_submit_usage_receipt(&env, receipt, public_key, signature)
+    }
+
+    /// Merchant registers an ed25519 meter key that signs usage receipts, for
+    /// all their subscriptions (`plan_id = None`) or one of their plans.
+    pub fn register_meter_key(
+        env: Env,
+        merchant: Address,
+        public_key: BytesN<32>,
+        plan_id: Option<u32>,
+    ) -> Result<(), Error> {
+        meters::do_register_meter_key(&env, merchant, public_key, plan_id)
+    }
+
+    /// Merchant revokes a receipt-signing meter key.
+    pub fn revoke_meter_key(
+        env: Env,
+        merchant: Address,
+        public_key: BytesN<32>,
+        plan_id: Option<u32>,
+    ) -> Result<(), Error> {
+        meters::do_revoke_meter_key(&env, merchant, public_key, plan_id)
+    }
+
+    /// Merchant debits an ad-hoc `amount` (setup fee, add-on) from the prepaid
+    /// balance, within the ceilings the subscriber set with
+    /// [`Self::set_one_off_limits`]. Emits `OneOffChargedEvent`.
+    pub fn charge_one_off(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        oneoff::do_charge_one_off(&env, subscription_id, merchant, amount)
+    }
+
+    /// Subscriber pre-authorizes one-off charges: at most `per_charge` each and
+    /// `per_period` in total per billing period. Zero disables them.
+    pub fn set_one_off_limits(
+        env: Env,
+        subscription_id: u32,
+        per_charge: i128,
+        per_period: i128,
+    ) -> Result<(), Error> {
+        oneoff::do_set_one_off_limits(&env, subscription_id, per_charge, per_period)
+    }
+
+    /// Subscriber's one-off ceilings for the subscription (zero unless set).
+    pub fn get_one_off_limits(env: Env, subscription_id: u32) -> OneOffLimits {
+        oneoff::get_one_off_limits(&env, subscription_id)
     }
 
     // ── Merchant ─────────────────────────────────────────────────────────
 
     /// Merchant withdraws accumulated USDC to their wallet.
+    ///
+    /// Debits the merchant's internal balance and transfers the tokens. Fails with
+    /// `InsufficientBalance` if `amount` exceeds what charges have credited.
     pub fn withdraw_merchant_funds(env: Env, merchant: Address, amount: i128) -> Result<(), Error> {
         merchant::withdraw_merchant_funds(&env, merchant, amount)
     }
 
+    /// Get the merchant's claimable balance (credited by charges, debited by withdrawals).
+    pub fn get_merchant_balance(env: Env, merchant: Address) -> i128 {
+        merchant::get_merchant_balance(&env, &merchant)
+    }
+
+    /// Merchant refunds `amount` of the charge for billing `period`, paid from
+    /// their claimable balance into the subscription's prepaid balance or the
+    /// subscriber's wallet. `reason` is a merchant-defined code. Total refunds
+    /// cannot exceed what the subscription was charged.
+    pub fn refund(
+        env: Env,
+        subscription_id: u32,
+        amount: i128,
+        period: u64,
+        reason: u32,
+        destination: RefundDestination,
+    ) -> Result<(), Error> {
+        refunds::do_refund(&env, subscription_id, amount, period, reason, destination)
+    }
+
+    /// Merchant opts in (or out) of refunding the unused part of the current
+    /// period when a subscription is cancelled.
+    pub fn set_prorated_cancel_refunds(
+        env: Env,
+        merchant: Address,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        refunds::do_set_prorated_cancel_refunds(&env, merchant, enabled)
+    }
+
+    /// Whether the merchant refunds unused time on cancellation (off by default).
+    pub fn get_prorated_cancel_refunds(env: Env, merchant: Address) -> bool {
+        refunds::get_prorated_cancel_refunds(&env, &merchant)
+    }
+
+    // ── Plans ────────────────────────────────────────────────────────────
+
+    /// Merchant publishes a plan (price, interval, usage flag, optional name and
+    /// metadata hash). Returns the new plan ID.
+    pub fn create_plan(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        name: Option<String>,
+        metadata_hash: Option<BytesN<32>>,
+    ) -> Result<u32, Error> {
+        plans::do_create_plan(
+            &env,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            name,
+            metadata_hash,
+        )
+    }
+
+    /// Set the trial offered to new subscribers of a plan (`trial_seconds == 0`
+    /// removes it). `trial_amount` is the per-interval trial price; `None` is free.
+    pub fn set_plan_trial(
+        env: Env,
+        merchant: Address,
+        plan_id: u32,
+        trial_seconds: u64,
+        trial_amount: Option<i128>,
+    ) -> Result<(), Error> {
+        trials::do_set_plan_trial(&env, merchant, plan_id, trial_seconds, trial_amount)
+    }
+
+    /// Merchant activates or deactivates one of their plans.
+    pub fn set_plan_active(
+        env: Env,
+        merchant: Address,
+        plan_id: u32,
+        active: bool,
+    ) -> Result<(), Error> {
+        plans::do_set_plan_active(&env, merchant, plan_id, active)
+    }
+
+    /// Read a plan by id.
+    pub fn get_plan(env: Env, plan_id: u32) -> Result<Plan, Error> {
+        plans::get_plan(&env, plan_id)
+    }
+
+    /// Merchant schedules a new price for every subscription on a plan.
+    ///
+    /// `effective_at` must be at least the notice period away. Subscriptions switch
+    /// to `new_amount` from the first billing period starting at or after it.
+    pub fn schedule_plan_price_change(
+        env: Env,
+        merchant: Address,
+        plan_id: u32,
+        new_amount: i128,
+        effective_at: u64,
+    ) -> Result<(), Error> {
+        pricing::do_schedule_plan_price_change(&env, merchant, plan_id, new_amount, effective_at)
+    }
+
+    /// Merchant schedules a new price for a single subscription.
+    ///
+    /// Same notice rules as [`Self::schedule_plan_price_change`]. The subscriber
+    /// may cancel before `effective_at` and is never charged the new price.
+    pub fn schedule_price_change(
+        env: Env,
+        merchant: Address,
+        subscription_id: u32,
+        new_amount: i128,
+        effective_at: u64,
+    ) -> Result<(), Error> {
+        pricing::do_schedule_subscription_price_change(
+            &env,
+            merchant,
+            subscription_id,
+            new_amount,
+            effective_at,
+        )
+    }
+
+    /// Return the announced price change not yet charged to this subscription, if any.
+    pub fn get_pending_price_change(
+        env: Env,
+        subscription_id: u32,
+    ) -> Result<Option<PriceChange>, Error> {
+        pricing::get_pending_price_change(&env, subscription_id)
+    }
+
+    /// Return a merchant's plans in creation order, paginated.
+    pub fn list_merchant_plans(env: Env, merchant: Address, start: u32, limit: u32) -> Vec<Plan> {
+        plans::list_merchant_plans(&env, merchant, start, limit)
+    }
+
+    /// Return the total number of plans a merchant has created.
+    pub fn get_merchant_plan_count(env: Env, merchant: Address) -> u32 {
+        plans::get_merchant_plan_count(&env, &merchant)
+    }
+
+    // ── Dunning ──────────────────────────────────────────────────────────
+
+    /// Merchant sets the dunning policy for their own subscriptions.
+    pub fn set_merchant_dunning_policy(
+        env: Env,
+        merchant: Address,
+        policy: DunningPolicy,
+    ) -> Result<(), Error> {
+        dunning::do_set_merchant_dunning_policy(&env, merchant, policy)
+    }
+
+    /// Effective dunning policy for a merchant: their own, else the global one.
+    pub fn get_dunning_policy(env: Env, merchant: Address) -> Option<DunningPolicy> {
+        dunning::get_dunning_policy(&env, &merchant)
+    }
+
+    // ── Catch-up ─────────────────────────────────────────────────────────
+
+    /// Merchant chooses how missed billing periods are charged by
+    /// `charge_subscription_catch_up` (default `Forgive`).
+    pub fn set_catch_up_policy(
+        env: Env,
+        merchant: Address,
+        policy: CatchUpPolicy,
+    ) -> Result<(), Error> {
+        catch_up::do_set_catch_up_policy(&env, merchant, policy)
+    }
+
+    /// Merchant's catch-up policy.
+    pub fn get_catch_up_policy(env: Env, merchant: Address) -> CatchUpPolicy {
+        catch_up::get_catch_up_policy(&env, &merchant)
+    }
+
+    // ── Lifecycle authorization ──────────────────────────────────────────
+
+    /// Merchant sets which parties (subscriber, merchant, admin, delegated
+    /// manager) may pause, resume and cancel their subscriptions.
+    pub fn set_lifecycle_auth_policy(
+        env: Env,
+        merchant: Address,
+        policy: LifecycleAuthPolicy,
+    ) -> Result<(), Error> {
+        auth_policy::do_set_lifecycle_auth_policy(&env, merchant, policy)
+    }
+
+    /// Merchant's lifecycle authorization policy (subscriber and merchant by default).
+    pub fn get_lifecycle_auth_policy(env: Env, merchant: Address) -> LifecycleAuthPolicy {
+        auth_policy::get_lifecycle_auth_policy(&env, &merchant)
+    }
+
+    // ── Pauses ───────────────────────────────────────────────────────────
+
+    /// Merchant caps how long their subscriptions may be paused (0 = unlimited).
+    pub fn set_max_pause_duration(env: Env, merchant: Address, seconds: u64) -> Result<(), Error> {
+        pause::do_set_max_pause_duration(&env, merchant, seconds)
+    }
+
+    /// Merchant's maximum pause length in seconds (0 = unlimited).
+    pub fn get_max_pause_duration(env: Env, merchant: Address) -> u64 {
+        pause::get_max_pause_duration(&env, &merchant)
+    }
+
+    // ── Coupons ──────────────────────────────────────────────────────────
+
+    /// Merchant registers a coupon `code`: a discount applied to the next `cycles`
+    /// full-price charges of each subscription that redeems it. `max_redemptions`
+    /// of 0 means unlimited; redemptions are rejected at or after `expires_at`.
+    pub fn create_coupon(
+        env: Env,
+        merchant: Address,
+        code: Symbol,
+        discount: Discount,
+        cycles: u32,
+        max_redemptions: u32,
+        expires_at: Option<u64>,
+    ) -> Result<(), Error> {
+        coupons::do_create_coupon(
+            &env,
+            merchant,
+            code,
+            discount,
+            cycles,
+            max_redemptions,
+            expires_at,
+        )
+    }
+
+    /// Merchant stops new redemptions of a coupon.
+    pub fn deactivate_coupon(env: Env, merchant: Address, code: Symbol) -> Result<(), Error> {
+        coupons::do_deactivate_coupon(&env, merchant, code)
+    }
+
+    /// Read a merchant's coupon by code.
+    pub fn get_coupon(env: Env, merchant: Address, code: Symbol) -> Result<Coupon, Error> {
+        coupons::get_coupon(&env, &merchant, &code)
+    }
+
     // ── Queries ──────────────────────────────────────────────────────────
 
     /// Read subscription by id.
@@ -220,9 +874,15 @@ impl SubscriptionVault {
     }
 
     /// Get estimated next charge info (timestamp + whether charge is expected).
+    /// The amount reflects any scheduled price change in force by then.
     pub fn get_next_charge_info(env: Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
-        let sub = queries::get_subscription(&env, subscription_id)?;
-        Ok(compute_next_charge_info(&sub))
+        queries::get_next_charge_info(&env, subscription_id)
+    }
+
+    /// Compare tracked liabilities (prepaid + merchant balances) against the
+    /// contract's actual token balance.
+    pub fn get_solvency_report(env: Env) -> Result<SolvencyReport, Error> {
+        accounting::get_solvency_report(&env)
     }
 
     /// Return subscriptions for a merchant, paginated.
@@ -254,7 +914,7 @@ impl SubscriptionVault {
     /// A `SubscriptionsPage` containing subscription IDs and pagination metadata
     ///
     /// # Performance Notes
-    /// - Time complexity: O(n) where n = total subscriptions in contract
+    /// - Time complexity: O(log n + limit) where n = subscriptions owned by `subscriber`
     /// - Space complexity: O(limit)
     /// - Suitable for off-chain indexers and UI pagination
     ///
@@ -1,14 +1,55 @@
-//! Merchant entrypoints: withdraw_merchant_funds.
+//! Merchant entrypoints: withdraw_merchant_funds, merchant balance ledger.
 //!
 //! **PRs that only change merchant payouts should edit this file only.**
+//!
+//! Every successful charge (interval or usage) credits the merchant's internal
+//! balance. Withdrawals are checked against and debited from that balance before
+//! tokens leave the contract, so a merchant can never withdraw more than it earned.
 
-use crate::safe_math::validate_non_negative;
-use crate::types::Error;
+use crate::accounting::{decrease_merchant_liabilities, increase_merchant_liabilities};
+use crate::admin::get_token;
+use crate::safe_math::{safe_add_balance, safe_sub_balance};
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error};
 use soroban_sdk::{Address, Env, Symbol};
 
+/// Returns the merchant's claimable balance (0 if never credited).
+pub fn get_merchant_balance(env: &Env, merchant: &Address) -> i128 {
+    read_persistent(env, &DataKey::MerchantBalance(merchant.clone())).unwrap_or(0)
+}
+
+fn set_merchant_balance(env: &Env, merchant: &Address, balance: i128) {
+    write_persistent(env, &DataKey::MerchantBalance(merchant.clone()), &balance);
+}
+
+/// Credits `amount` to the merchant's claimable balance. Called by the charge paths.
+pub fn credit_merchant_balance(env: &Env, merchant: &Address, amount: i128) -> Result<(), Error> {
+    let balance = safe_add_balance(get_merchant_balance(env, merchant), amount)?;
+    set_merchant_balance(env, merchant, balance);
+    increase_merchant_liabilities(env, amount)
+}
+
+/// Debits `amount` from the merchant's claimable balance, failing with
+/// `InsufficientBalance` if the merchant has not earned (or already withdrew) it.
+pub fn debit_merchant_balance(env: &Env, merchant: &Address, amount: i128) -> Result<(), Error> {
+    let balance = get_merchant_balance(env, merchant);
+    let new_balance = safe_sub_balance(balance, amount).map_err(|_| Error::InsufficientBalance)?;
+    set_merchant_balance(env, merchant, new_balance);
+    decrease_merchant_liabilities(env, amount)
+}
+
 pub fn withdraw_merchant_funds(env: &Env, merchant: Address, amount: i128) -> Result<(), Error> {
     merchant.require_auth();
-    validate_non_negative(amount)?;
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    // Debit before the external transfer (checks-effects-interactions).
+    debit_merchant_balance(env, &merchant, amount)?;
+
+    let token_client = soroban_sdk::token::Client::new(env, &get_token(env)?);
+    token_client.transfer(&env.current_contract_address(), &merchant, &amount);
+
     env.events()
         .publish((Symbol::new(env, "withdrawn"), merchant.clone()), amount);
     Ok(())
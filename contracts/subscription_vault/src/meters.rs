@@ -0,0 +1,185 @@
+//! Usage meters: the addresses allowed to report usage via `charge_usage`.
+//!
+//! **PRs that only change who may report usage should edit this file only.**
+//!
+//! A merchant registers meter addresses (the keys of their metering service)
+//! either for all of their subscriptions or for the subscriptions of one plan.
+//! `charge_usage` requires auth from a meter registered for the subscription's
+//! merchant or plan. Meters can be rotated (replaced in one call) or revoked.
+//!
+//! Meters that sign usage receipts instead of sending transactions are
+//! registered by their ed25519 public key (see [`crate::receipts`]).
+
+use crate::plans::get_plan;
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error, Subscription};
+use soroban_sdk::{Address, BytesN, Env, Symbol};
+
+fn meter_entry(merchant: &Address, meter: &Address, plan_id: Option<u32>) -> DataKey {
+    match plan_id {
+        Some(plan_id) => DataKey::PlanMeter(plan_id, meter.clone()),
+        None => DataKey::MerchantMeter(merchant.clone(), meter.clone()),
+    }
+}
+
+/// Requires `merchant`'s auth and, for a plan meter, that they own the plan.
+fn authorize_merchant(env: &Env, merchant: &Address, plan_id: Option<u32>) -> Result<(), Error> {
+    merchant.require_auth();
+    if let Some(plan_id) = plan_id {
+        if get_plan(env, plan_id)?.merchant != *merchant {
+            return Err(Error::Unauthorized);
+        }
+    }
+    Ok(())
+}
+
+pub fn is_meter_registered(
+    env: &Env,
+    merchant: &Address,
+    meter: &Address,
+    plan_id: Option<u32>,
+) -> bool {
+    read_persistent::<bool>(env, &meter_entry(merchant, meter, plan_id)).is_some()
+}
+
+fn add_meter(env: &Env, merchant: &Address, meter: &Address, plan_id: Option<u32>) {
+    write_persistent(env, &meter_entry(merchant, meter, plan_id), &true);
+}
+
+fn remove_meter(
+    env: &Env,
+    merchant: &Address,
+    meter: &Address,
+    plan_id: Option<u32>,
+) -> Result<(), Error> {
+    if !is_meter_registered(env, merchant, meter, plan_id) {
+        return Err(Error::NotFound);
+    }
+    env.storage()
+        .persistent()
+        .remove(&meter_entry(merchant, meter, plan_id));
+    Ok(())
+}
+
+pub fn do_register_meter(
+    env: &Env,
+    merchant: Address,
+    meter: Address,
+    plan_id: Option<u32>,
+) -> Result<(), Error> {
+    authorize_merchant(env, &merchant, plan_id)?;
+    add_meter(env, &merchant, &meter, plan_id);
+    env.events().publish(
+        (Symbol::new(env, "meter_registered"), merchant),
+        (meter, plan_id),
+    );
+    Ok(())
+}
+
+pub fn do_revoke_meter(
+    env: &Env,
+    merchant: Address,
+    meter: Address,
+    plan_id: Option<u32>,
+) -> Result<(), Error> {
+    authorize_merchant(env, &merchant, plan_id)?;
+    remove_meter(env, &merchant, &meter, plan_id)?;
+    env.events().publish(
+        (Symbol::new(env, "meter_revoked"), merchant),
+        (meter, plan_id),
+    );
+    Ok(())
+}
+
+/// Replaces `old_meter` with `new_meter` in one step, so no usage reports are
+/// rejected while a metering key is rotated.
+pub fn do_rotate_meter(
+    env: &Env,
+    merchant: Address,
+    old_meter: Address,
+    new_meter: Address,
+    plan_id: Option<u32>,
+) -> Result<(), Error> {
+    authorize_merchant(env, &merchant, plan_id)?;
+    remove_meter(env, &merchant, &old_meter, plan_id)?;
+    add_meter(env, &merchant, &new_meter, plan_id);
+    env.events().publish(
+        (Symbol::new(env, "meter_rotated"), merchant),
+        (old_meter, new_meter, plan_id),
+    );
+    Ok(())
+}
+
+/// Requires `meter`'s auth and checks it is registered for the subscription's
+/// merchant or plan.
+pub fn require_meter(env: &Env, sub: &Subscription, meter: &Address) -> Result<(), Error> {
+    meter.require_auth();
+    let registered = is_meter_registered(env, &sub.merchant, meter, None)
+        || sub
+            .plan_id
+            .is_some_and(|plan_id| is_meter_registered(env, &sub.merchant, meter, Some(plan_id)));
+    if registered {
+        Ok(())
+    } else {
+        Err(Error::Unauthorized)
+    }
+}
+
+fn key_entry(merchant: &Address, public_key: &BytesN<32>, plan_id: Option<u32>) -> DataKey {
+    match plan_id {
+        Some(plan_id) => DataKey::PlanMeterKey(plan_id, public_key.clone()),
+        None => DataKey::MerchantMeterKey(merchant.clone(), public_key.clone()),
+    }
+}
+
+pub fn is_meter_key_registered(
+    env: &Env,
+    merchant: &Address,
+    public_key: &BytesN<32>,
+    plan_id: Option<u32>,
+) -> bool {
+    read_persistent::<bool>(env, &key_entry(merchant, public_key, plan_id)).is_some()
+}
+
+pub fn do_register_meter_key(
+    env: &Env,
+    merchant: Address,
+    public_key: BytesN<32>,
+    plan_id: Option<u32>,
+) -> Result<(), Error> {
+    authorize_merchant(env, &merchant, plan_id)?;
+    write_persistent(env, &key_entry(&merchant, &public_key, plan_id), &true);
+    env.events().publish(
+        (Symbol::new(env, "meter_key_registered"), merchant),
+        (public_key, plan_id),
+    );
+    Ok(())
+}
+
+pub fn do_revoke_meter_key(
+    env: &Env,
+    merchant: Address,
+    public_key: BytesN<32>,
+    plan_id: Option<u32>,
+) -> Result<(), Error> {
+    authorize_merchant(env, &merchant, plan_id)?;
+    if !is_meter_key_registered(env, &merchant, &public_key, plan_id) {
+        return Err(Error::NotFound);
+    }
+    env.storage()
+        .persistent()
+        .remove(&key_entry(&merchant, &public_key, plan_id));
+    env.events().publish(
+        (Symbol::new(env, "meter_key_revoked"), merchant),
+        (public_key, plan_id),
+    );
+    Ok(())
+}
+
+/// Whether `public_key` is registered for the subscription's merchant or plan.
+pub fn is_meter_key_for(env: &Env, sub: &Subscription, public_key: &BytesN<32>) -> bool {
+    is_meter_key_registered(env, &sub.merchant, public_key, None)
+        || sub.plan_id.is_some_and(|plan_id| {
+            is_meter_key_registered(env, &sub.merchant, public_key, Some(plan_id))
+        })
+}
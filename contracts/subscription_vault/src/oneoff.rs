@@ -0,0 +1,109 @@
+//! Merchant one-off charges (setup fees, add-ons) against the prepaid balance.
+//!
+//! **PRs that only change one-off charging should edit this file only.**
+//!
+//! The subscriber pre-authorizes one-off charges per subscription with two
+//! ceilings: a maximum per charge and a maximum total per billing period. Both
+//! default to zero, so a merchant cannot charge anything until the subscriber
+//! opts in. The period is the anchored billing period containing the charge
+//! (time before the first period counts as one period of its own). Only an
+//! Active subscription can be charged: a paused one is not billed at all.
+
+use crate::accounting::decrease_prepaid_liabilities;
+use crate::merchant::credit_merchant_balance;
+use crate::queries::get_subscription;
+use crate::refunds::record_charge_at;
+use crate::safe_math::{safe_add_balance, safe_sub_balance, validate_non_negative};
+use crate::schedule::period_at;
+use crate::storage::{read_persistent, save_subscription, write_persistent};
+use crate::types::{
+    DataKey, Error, OneOffChargedEvent, OneOffLimits, PeriodSpend, SubscriptionStatus,
+};
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
+
+pub fn do_set_one_off_limits(
+    env: &Env,
+    subscription_id: u32,
+    per_charge: i128,
+    per_period: i128,
+) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    validate_non_negative(per_charge)?;
+    validate_non_negative(per_period)?;
+
+    let limits = OneOffLimits {
+        per_charge,
+        per_period,
+    };
+    write_persistent(env, &DataKey::OneOffLimits(subscription_id), &limits);
+    env.events().publish(
+        (Symbol::new(env, "one_off_limits_set"), subscription_id),
+        limits,
+    );
+    Ok(())
+}
+
+/// The subscriber's one-off ceilings; both zero unless set.
+pub fn get_one_off_limits(env: &Env, subscription_id: u32) -> OneOffLimits {
+    read_persistent(env, &DataKey::OneOffLimits(subscription_id)).unwrap_or(OneOffLimits {
+        per_charge: 0,
+        per_period: 0,
+    })
+}
+
+pub fn do_charge_one_off(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    amount: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let mut sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let limits = get_one_off_limits(env, subscription_id);
+    let now = env.ledger().timestamp();
+    let period = period_at(&sub, now);
+    let spent = read_persistent::<PeriodSpend>(env, &DataKey::OneOffSpend(subscription_id))
+        .filter(|spend| spend.period == period)
+        .map_or(0, |spend| spend.amount);
+    let spent = safe_add_balance(spent, amount)?;
+    if amount > limits.per_charge || spent > limits.per_period {
+        return Err(Error::OneOffLimitExceeded);
+    }
+
+    sub.prepaid_balance =
+        safe_sub_balance(sub.prepaid_balance, amount).map_err(|_| Error::InsufficientBalance)?;
+    sub.total_charged = safe_add_balance(sub.total_charged, amount)?;
+    save_subscription(env, subscription_id, &sub);
+    write_persistent(
+        env,
+        &DataKey::OneOffSpend(subscription_id),
+        &PeriodSpend {
+            period,
+            amount: spent,
+        },
+    );
+    decrease_prepaid_liabilities(env, amount)?;
+    credit_merchant_balance(env, &merchant, amount)?;
+    record_charge_at(env, subscription_id, &sub, now, amount)?;
+
+    env.events().publish(
+        (symbol_short!("oneoff_ch"),),
+        OneOffChargedEvent {
+            subscription_id,
+            merchant,
+            amount,
+        },
+    );
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! Timed pauses: auto-resume and shifting the billing clock by the paused time.
+//!
+//! **PRs that only change pause/resume behaviour should edit this file only.**
+//!
+//! A pause records `paused_at` and, optionally, `resume_at`. On resume the
+//! billing clock (`billing_anchor` and `last_payment_timestamp`) moves forward
+//! by the time spent paused, so the subscriber keeps the unused days of the
+//! period they paid for instead of being charged immediately. Calendar
+//! cadences move by whole periods instead, one for each billing date that
+//! passed while paused, so billing stays on the configured day and the paid
+//! period keeps its number.
+//!
+//! A pause with `resume_at` ends by itself: once `resume_at` is reached the
+//! subscription is treated as resumed whenever it is loaded (e.g. by
+//! `charge_subscription`) and persisted the next time it is saved. A merchant
+//! may cap pause length with `set_max_pause_duration`; pauses without
+//! `resume_at` then auto-resume after the maximum.
+
+use crate::auth_policy::{require_lifecycle_auth, LifecycleAction};
+use crate::queries::get_subscription;
+use crate::schedule::{period_at, period_start};
+use crate::state_machine::validate_status_transition;
+use crate::storage::{read_persistent, save_subscription, write_persistent};
+use crate::types::{BillingCadence, DataKey, Error, Subscription, SubscriptionStatus};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn do_set_max_pause_duration(env: &Env, merchant: Address, seconds: u64) -> Result<(), Error> {
+    merchant.require_auth();
+    write_persistent(env, &DataKey::MerchantMaxPause(merchant.clone()), &seconds);
+    env.events()
+        .publish((Symbol::new(env, "max_pause_set"), merchant), seconds);
+    Ok(())
+}
+
+/// The merchant's maximum pause length in seconds; 0 means unlimited.
+pub fn get_max_pause_duration(env: &Env, merchant: &Address) -> u64 {
+    read_persistent(env, &DataKey::MerchantMaxPause(merchant.clone())).unwrap_or(0)
+}
+
+/// Pauses a subscription until `resume_at` (indefinitely when `None`, unless
+/// the merchant caps pause length). Pausing again while paused only changes
+/// when it resumes.
+pub fn do_pause_until(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: Address,
+    resume_at: Option<u64>,
+) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    require_lifecycle_auth(env, &sub, &authorizer, LifecycleAction::Pause)?;
+    validate_status_transition(&sub.status, &SubscriptionStatus::Paused)?;
+
+    let now = env.ledger().timestamp();
+    let paused_at = sub.paused_at.unwrap_or(now);
+    let max = get_max_pause_duration(env, &sub.merchant);
+    let resume_at = match resume_at {
+        None if max > 0 => Some(paused_at.saturating_add(max)),
+        other => other,
+    };
+    if let Some(resume_at) = resume_at {
+        if resume_at <= now || (max > 0 && resume_at - paused_at > max) {
+            return Err(Error::InvalidPause);
+        }
+    }
+
+    sub.status = SubscriptionStatus::Paused;
+    sub.paused_at = Some(paused_at);
+    sub.resume_at = resume_at;
+    save_subscription(env, subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "paused"), subscription_id),
+        (authorizer, resume_at),
+    );
+    Ok(())
+}
+
+/// Moves a paused subscription back to `Active` as of `resumed_at`, shifting
+/// the billing clock by the paused duration. The caller saves it.
+pub fn resume_billing_clock(sub: &mut Subscription, resumed_at: u64) -> Result<(), Error> {
+    validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
+    let paused_at = sub.paused_at.unwrap_or(resumed_at);
+    if sub.cadence == BillingCadence::Fixed {
+        let paused_for = resumed_at.saturating_sub(paused_at);
+        sub.billing_anchor = sub
+            .billing_anchor
+            .checked_add(paused_for)
+            .ok_or(Error::Overflow)?;
+        sub.last_payment_timestamp = sub
+            .last_payment_timestamp
+            .checked_add(paused_for)
+            .ok_or(Error::Overflow)?;
+    } else {
+        skip_calendar_periods(sub, paused_at, resumed_at)?;
+    }
+    sub.status = SubscriptionStatus::Active;
+    sub.paused_at = None;
+    sub.resume_at = None;
+    Ok(())
+}
+
+/// Moves a calendar schedule forward by the billing dates that passed between
+/// `paused_at` and `resumed_at`. The anchor stays on a billing date and the
+/// paid period keeps its number, so the recorded charges still line up.
+fn skip_calendar_periods(
+    sub: &mut Subscription,
+    paused_at: u64,
+    resumed_at: u64,
+) -> Result<(), Error> {
+    let skipped = match (period_at(sub, paused_at), period_at(sub, resumed_at)) {
+        (_, None) => 0,
+        (None, Some(after)) => after + 1 - sub.anchor_period,
+        (Some(before), Some(after)) => after - before,
+    };
+    if skipped == 0 {
+        return Ok(());
+    }
+    let paid = period_at(sub, sub.last_payment_timestamp);
+    sub.billing_anchor = period_start(sub, sub.anchor_period + skipped)?;
+    if let Some(paid) = paid {
+        sub.last_payment_timestamp = period_start(sub, paid)?;
+    }
+    Ok(())
+}
+
+/// Applies an automatic resume whose `resume_at` has passed. Returns `true` if
+/// `sub` was resumed; the caller decides whether to save it.
+pub fn apply_due_resume(sub: &mut Subscription, now: u64) -> bool {
+    match sub.resume_at {
+        Some(resume_at) if sub.status == SubscriptionStatus::Paused && now >= resume_at => {
+            resume_billing_clock(sub, resume_at).is_ok()
+        }
+        _ => false,
+    }
+}
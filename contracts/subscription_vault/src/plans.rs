@@ -0,0 +1,123 @@
+//! Merchant plan catalog: create, activate/deactivate, list.
+//!
+//! **PRs that only change the plan catalog should edit this file only.**
+//!
+//! A plan is a merchant-owned price list entry. Subscribers subscribe by
+//! `plan_id` (see [`crate::subscription::do_create_subscription_from_plan`]),
+//! so pricing is set by the merchant and auditable on-chain.
+
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error, Plan};
+use soroban_sdk::{Address, BytesN, Env, String, Symbol, Vec};
+
+fn next_plan_id(env: &Env) -> u32 {
+    let key = Symbol::new(env, "next_plan_id");
+    let id: u32 = env.storage().instance().get(&key).unwrap_or(0);
+    env.storage().instance().set(&key, &(id + 1));
+    id
+}
+
+pub fn get_plan(env: &Env, plan_id: u32) -> Result<Plan, Error> {
+    read_persistent(env, &DataKey::Plan(plan_id)).ok_or(Error::NotFound)
+}
+
+pub fn save_plan(env: &Env, plan: &Plan) {
+    write_persistent(env, &DataKey::Plan(plan.plan_id), plan);
+}
+
+/// Loads a plan and checks that `merchant` owns it.
+pub fn get_owned_plan(env: &Env, merchant: &Address, plan_id: u32) -> Result<Plan, Error> {
+    let plan = get_plan(env, plan_id)?;
+    if plan.merchant != *merchant {
+        return Err(Error::Unauthorized);
+    }
+    Ok(plan)
+}
+
+pub fn do_create_plan(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    name: Option<String>,
+    metadata_hash: Option<BytesN<32>>,
+) -> Result<u32, Error> {
+    merchant.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if interval_seconds == 0 {
+        return Err(Error::InvalidInterval);
+    }
+
+    let plan_id = next_plan_id(env);
+    let plan = Plan {
+        plan_id,
+        merchant: merchant.clone(),
+        amount,
+        interval_seconds,
+        usage_enabled,
+        name,
+        metadata_hash,
+        active: true,
+        trial_seconds: 0,
+        trial_amount: 0,
+    };
+    save_plan(env, &plan);
+
+    // Maintain merchant → plan-ID index
+    let count_key = DataKey::MerchantPlanCount(merchant.clone());
+    let len: u32 = read_persistent(env, &count_key).unwrap_or(0);
+    write_persistent(
+        env,
+        &DataKey::MerchantPlanAt(merchant.clone(), len),
+        &plan_id,
+    );
+    write_persistent(env, &count_key, &(len + 1));
+
+    env.events().publish(
+        (Symbol::new(env, "plan_created"), merchant),
+        (plan_id, amount, interval_seconds, usage_enabled),
+    );
+    Ok(plan_id)
+}
+
+/// Activates or deactivates a plan. Inactive plans accept no new subscribers;
+/// existing subscriptions keep billing.
+pub fn do_set_plan_active(
+    env: &Env,
+    merchant: Address,
+    plan_id: u32,
+    active: bool,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let mut plan = get_owned_plan(env, &merchant, plan_id)?;
+    plan.active = active;
+    save_plan(env, &plan);
+    env.events().publish(
+        (Symbol::new(env, "plan_active_set"), merchant),
+        (plan_id, active),
+    );
+    Ok(())
+}
+
+pub fn get_merchant_plan_count(env: &Env, merchant: &Address) -> u32 {
+    read_persistent(env, &DataKey::MerchantPlanCount(merchant.clone())).unwrap_or(0)
+}
+
+/// Returns a merchant's plans in creation order, paginated by offset.
+pub fn list_merchant_plans(env: &Env, merchant: Address, start: u32, limit: u32) -> Vec<Plan> {
+    let len = get_merchant_plan_count(env, &merchant);
+    let end = start.saturating_add(limit).min(len);
+
+    let mut result = Vec::new(env);
+    for i in start..end {
+        let plan = read_persistent::<u32>(env, &DataKey::MerchantPlanAt(merchant.clone(), i))
+            .and_then(|id| read_persistent::<Plan>(env, &DataKey::Plan(id)));
+        if let Some(plan) = plan {
+            result.push_back(plan);
+        }
+    }
+    result
+}
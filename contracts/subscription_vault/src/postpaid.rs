@@ -0,0 +1,90 @@
+//! Postpaid usage: accrue usage during the period, settle it with the next charge.
+//!
+//! **PRs that only change usage billed in arrears should edit this file only.**
+//!
+//! With `usage_in_arrears` set, usage reports (`charge_usage`, usage receipts
+//! and `report_usage_units`) pass the usual checks and usage caps but move no
+//! money: the amount is added to the subscription's `pending_usage`. The next
+//! interval charge debits the base amount plus the pending usage in one go and
+//! emits a single `charged` event carrying both. If the balance cannot cover
+//! the total, the charge fails like any other and the usage stays pending for
+//! the retry. When the subscription ends — cancelled directly, by a scheduled
+//! cancellation or by dunning, or completed at the end of its term — whatever
+//! pending usage the balance covers is settled and the rest is written off.
+//! A scheduled cancellation is applied lazily, so its usage is settled at the
+//! latest when the subscriber withdraws the remaining balance.
+
+use crate::accounting::decrease_prepaid_liabilities;
+use crate::merchant::credit_merchant_balance;
+use crate::queries::get_subscription;
+use crate::refunds::record_charge_at;
+use crate::safe_math::{safe_add, safe_add_balance, safe_sub_balance};
+use crate::storage::save_subscription;
+use crate::types::{Error, Subscription};
+use soroban_sdk::{Env, Symbol};
+
+/// Switches usage billing between immediate debits and accrual in arrears.
+/// Changes the agreed terms, so both parties authorize.
+pub fn do_set_usage_in_arrears(
+    env: &Env,
+    subscription_id: u32,
+    enabled: bool,
+) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    sub.merchant.require_auth();
+    sub.usage_in_arrears = enabled;
+    save_subscription(env, subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "usage_arrears_set"), subscription_id),
+        enabled,
+    );
+    Ok(())
+}
+
+/// Interval `amount` plus the usage waiting to be settled with it.
+pub fn with_pending_usage(sub: &Subscription, amount: i128) -> Result<i128, Error> {
+    safe_add(amount, sub.pending_usage)
+}
+
+/// Adds reported usage to `pending_usage` and saves the subscription.
+pub fn accrue_usage(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    usage_amount: i128,
+) -> Result<(), Error> {
+    sub.pending_usage = safe_add_balance(sub.pending_usage, usage_amount)?;
+    save_subscription(env, subscription_id, sub);
+    env.events().publish(
+        (Symbol::new(env, "usage_accrued"), subscription_id),
+        (usage_amount, sub.pending_usage),
+    );
+    Ok(())
+}
+
+/// Debits as much pending usage as the balance covers and clears the rest.
+/// Returns the amount settled; the caller saves `sub`.
+pub fn settle_pending_usage(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+) -> Result<i128, Error> {
+    let settled = sub.pending_usage.min(sub.prepaid_balance);
+    let written_off = sub.pending_usage - settled;
+    sub.pending_usage = 0;
+    if settled > 0 {
+        sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, settled)?;
+        sub.total_charged = safe_add_balance(sub.total_charged, settled)?;
+        decrease_prepaid_liabilities(env, settled)?;
+        credit_merchant_balance(env, &sub.merchant, settled)?;
+        record_charge_at(env, subscription_id, sub, env.ledger().timestamp(), settled)?;
+    }
+    if settled > 0 || written_off > 0 {
+        env.events().publish(
+            (Symbol::new(env, "usage_settled"), subscription_id),
+            (settled, written_off),
+        );
+    }
+    Ok(settled)
+}
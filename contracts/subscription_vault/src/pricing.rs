@@ -0,0 +1,230 @@
+//! Scheduled price changes for plans and individual subscriptions.
+//!
+//! **PRs that only change how prices are changed over time should edit this file only.**
+//!
+//! A merchant announces a new price with an `effective_at` timestamp that must be
+//! at least the configured notice period in the future. Nothing changes until a
+//! billing period starting at or after `effective_at` is charged; subscribers who
+//! do not accept the new price can cancel before then and never pay it.
+//!
+//! When both the plan and the subscription have an effective change, the most
+//! recently scheduled one wins. Once a change is applied, the subscription
+//! records when it was scheduled and ignores changes scheduled before it; its
+//! own change is then removed. A plan's change is shared by every subscriber
+//! on the plan, so it stays until the merchant schedules the next one.
+
+use crate::admin::require_admin;
+use crate::plans::get_owned_plan;
+use crate::queries::get_subscription;
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error, Plan, PriceChange, Subscription, SubscriptionStatus};
+use soroban_sdk::{Address, Env, Symbol};
+
+/// Notice period used until admin configures one (30 days).
+pub const DEFAULT_PRICE_CHANGE_NOTICE: u64 = 30 * 24 * 60 * 60;
+
+pub fn get_price_change_notice(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "price_notice"))
+        .unwrap_or(DEFAULT_PRICE_CHANGE_NOTICE)
+}
+
+pub fn do_set_price_change_notice(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "price_notice"), &seconds);
+    env.events()
+        .publish((Symbol::new(env, "price_notice_updated"),), seconds);
+    Ok(())
+}
+
+fn new_price_change(env: &Env, new_amount: i128, effective_at: u64) -> Result<PriceChange, Error> {
+    if new_amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let now = env.ledger().timestamp();
+    let earliest = now
+        .checked_add(get_price_change_notice(env))
+        .ok_or(Error::Overflow)?;
+    if effective_at < earliest {
+        return Err(Error::NoticePeriodTooShort);
+    }
+    Ok(PriceChange {
+        new_amount,
+        effective_at,
+        scheduled_at: now,
+    })
+}
+
+pub fn do_schedule_plan_price_change(
+    env: &Env,
+    merchant: Address,
+    plan_id: u32,
+    new_amount: i128,
+    effective_at: u64,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    get_owned_plan(env, &merchant, plan_id)?;
+    let change = new_price_change(env, new_amount, effective_at)?;
+    write_persistent(env, &DataKey::PlanPriceChange(plan_id), &change);
+    env.events().publish(
+        (Symbol::new(env, "plan_price_scheduled"), plan_id),
+        (new_amount, effective_at),
+    );
+    Ok(())
+}
+
+pub fn do_schedule_subscription_price_change(
+    env: &Env,
+    merchant: Address,
+    subscription_id: u32,
+    new_amount: i128,
+    effective_at: u64,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotActive);
+    }
+    let change = new_price_change(env, new_amount, effective_at)?;
+    write_persistent(env, &DataKey::SubPriceChange(subscription_id), &change);
+    env.events().publish(
+        (Symbol::new(env, "price_scheduled"), subscription_id),
+        (sub.subscriber, new_amount, effective_at),
+    );
+    Ok(())
+}
+
+/// Returns the plan's price at `timestamp`, honoring a scheduled change.
+pub fn plan_amount_at(env: &Env, plan: &Plan, timestamp: u64) -> i128 {
+    match read_persistent::<PriceChange>(env, &DataKey::PlanPriceChange(plan.plan_id)) {
+        Some(change) if change.effective_at <= timestamp => change.new_amount,
+        _ => plan.amount,
+    }
+}
+
+fn latest_scheduled(a: Option<PriceChange>, b: Option<PriceChange>) -> Option<PriceChange> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.scheduled_at > a.scheduled_at {
+            b
+        } else {
+            a
+        }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Returns `(subscription change, plan change)` for a subscription, leaving
+/// out changes scheduled before the last one it applied.
+fn scheduled_changes(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+) -> (Option<PriceChange>, Option<PriceChange>) {
+    let is_new = |c: &PriceChange| {
+        sub.price_scheduled_at
+            .is_none_or(|applied| c.scheduled_at > applied)
+    };
+    let own = read_persistent::<PriceChange>(env, &DataKey::SubPriceChange(subscription_id))
+        .filter(is_new);
+    let plan = sub
+        .plan_id
+        .and_then(|id| read_persistent::<PriceChange>(env, &DataKey::PlanPriceChange(id)))
+        .filter(is_new);
+    (own, plan)
+}
+
+/// The change in force at `timestamp`: the most recently scheduled of the
+/// plan's and the subscription's changes that are effective by then.
+pub fn effective_price_change(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    timestamp: u64,
+) -> Option<PriceChange> {
+    let (own, plan) = scheduled_changes(env, subscription_id, sub);
+    let is_effective = |c: &PriceChange| c.effective_at <= timestamp;
+    latest_scheduled(own.filter(is_effective), plan.filter(is_effective))
+}
+
+/// Whether a change is scheduled to take effect after `timestamp`.
+pub fn has_upcoming_price_change(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    timestamp: u64,
+) -> bool {
+    let (own, plan) = scheduled_changes(env, subscription_id, sub);
+    let is_upcoming = |c: &PriceChange| c.effective_at > timestamp;
+    own.is_some_and(|c| is_upcoming(&c)) || plan.is_some_and(|c| is_upcoming(&c))
+}
+
+/// Switches `sub.amount` to the price in force for a billing period starting at
+/// `period_start` without touching storage, e.g. to estimate future charges.
+/// Returns the change applied.
+pub fn price_for_period(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    period_start: u64,
+) -> Option<PriceChange> {
+    let change = effective_price_change(env, subscription_id, sub, period_start)?;
+    sub.amount = change.new_amount;
+    sub.price_scheduled_at = Some(change.scheduled_at);
+    Some(change)
+}
+
+/// Switches `sub.amount` to the price in force for a billing period starting at
+/// `period_start` and removes the subscription's own change once it can no
+/// longer apply. Emits `price_changed` when the amount moves.
+pub fn apply_price_change(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    period_start: u64,
+) {
+    let old_amount = sub.amount;
+    let Some(change) = price_for_period(env, subscription_id, sub, period_start) else {
+        return;
+    };
+    let own_key = DataKey::SubPriceChange(subscription_id);
+    if read_persistent::<PriceChange>(env, &own_key)
+        .is_some_and(|own| own.scheduled_at <= change.scheduled_at)
+    {
+        env.storage().persistent().remove(&own_key);
+    }
+    if change.new_amount == old_amount {
+        return;
+    }
+    env.events().publish(
+        (Symbol::new(env, "price_changed"), subscription_id),
+        (old_amount, change.new_amount, change.effective_at),
+    );
+}
+
+/// Returns the announced change that the subscriber has not been charged yet:
+/// an upcoming change if one is scheduled, otherwise an effective change that
+/// will apply at the next charge.
+pub fn get_pending_price_change(
+    env: &Env,
+    subscription_id: u32,
+) -> Result<Option<PriceChange>, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let now = env.ledger().timestamp();
+    let (own, plan) = scheduled_changes(env, subscription_id, &sub);
+    let is_upcoming = |c: &PriceChange| c.effective_at > now;
+    let upcoming = latest_scheduled(own.filter(is_upcoming), plan.filter(is_upcoming));
+    Ok(upcoming.or_else(|| {
+        effective_price_change(env, subscription_id, &sub, now)
+            .filter(|change| change.new_amount != sub.amount)
+    }))
+}
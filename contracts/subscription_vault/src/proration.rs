@@ -0,0 +1,208 @@
+//! Mid-cycle plan changes (upgrades and downgrades) with proration.
+//!
+//! **PRs that only change how plan changes are priced should edit this file only.**
+//!
+//! Changing plan keeps the subscription ID, balance and history, and re-anchors
+//! billing at the time of the change:
+//! - **credit**: the unused share of what was paid for the current period, i.e.
+//!   `last_charge * (period_end - now) / old_interval`;
+//! - **charge**: the new plan's price for the period that starts now.
+//!
+//! As with every charge, the new period is paid in advance: the next charge falls
+//! one new interval after the change and pays for the period after it.
+//!
+//! The net is settled immediately against the prepaid balance: an upgrade debits
+//! it (and credits the merchant), a downgrade returns the difference to it (and
+//! debits the merchant). A downgrade never returns more than the merchant still
+//! holds; a merchant who has withdrawn forfeits nothing and the change goes
+//! through with a smaller credit. A subscription still in trial switches terms without
+//! proration, keeps its trial and is re-anchored at its next due time.
+//!
+//! A plan change counts as an interval charge towards a fixed term and drops any
+//! price change scheduled for the subscription alone (the new plan's price
+//! applies). Billing period numbers continue across the re-anchor, so records
+//! keyed by period never mix the old and new schedules.
+
+use crate::accounting::{decrease_prepaid_liabilities, increase_prepaid_liabilities};
+use crate::charge_core::{amount_due, roll_forward};
+use crate::merchant::{credit_merchant_balance, debit_merchant_balance, get_merchant_balance};
+use crate::plans::get_plan;
+use crate::postpaid::settle_pending_usage;
+use crate::pricing::plan_amount_at;
+use crate::queries::get_subscription;
+use crate::refunds::{record_period_charge, reduce_period_charge};
+use crate::safe_math::{safe_add, safe_add_balance, safe_prorate, safe_sub, safe_sub_balance};
+use crate::schedule::{next_charge_due, period_at, period_start};
+use crate::storage::{read_persistent, save_subscription, write_persistent};
+use crate::term::{beyond_term, emit_completed, record_cycle};
+use crate::trials::{end_trial, in_trial};
+use crate::types::{
+    BillingCadence, DataKey, Error, LastCharge, PlanChangedEvent, Subscription, SubscriptionStatus,
+};
+use soroban_sdk::{Env, Symbol};
+
+/// Unused share of the amount paid for the current period at `now`. Zero if the
+/// current period was never charged (e.g. the first interval after creation).
+pub fn unused_credit(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    now: u64,
+) -> Result<i128, Error> {
+    let Some(last) = read_persistent::<LastCharge>(env, &DataKey::LastCharge(subscription_id))
+    else {
+        return Ok(0);
+    };
+    // The paid period is the one containing the last payment (a pause shifts
+    // both together). Nothing is credited unless that period was charged.
+    let Some(period) = period_at(sub, sub.last_payment_timestamp) else {
+        return Ok(0);
+    };
+    if read_persistent::<u64>(env, &DataKey::ChargedPeriod(subscription_id)) != Some(period) {
+        return Ok(0);
+    }
+    let start = period_start(sub, period)?;
+    let end = period_start(sub, period.checked_add(1).ok_or(Error::Overflow)?)?;
+    let unused = end.saturating_sub(now).min(end - start);
+    safe_prorate(last.amount, unused, end - start)
+}
+
+pub fn do_change_plan(env: &Env, subscription_id: u32, new_plan_id: u32) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+    let plan = get_plan(env, new_plan_id)?;
+    if plan.merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+
+    let now = env.ledger().timestamp();
+    let trialing = in_trial(&sub, now);
+    if !trialing && beyond_term(&sub, now) {
+        return Err(Error::TermEnded);
+    }
+    let old_plan_id = sub.plan_id;
+    let old_amount = sub.amount;
+    let mut credit = if trialing {
+        0
+    } else {
+        unused_credit(env, subscription_id, &sub, now)?
+    };
+    // A trial keeps its due date; otherwise the new period starts now.
+    let anchor = if trialing {
+        next_charge_due(&sub)?
+    } else {
+        now
+    };
+    let first_period = next_period_number(env, subscription_id, &sub, now)?;
+    let paid_period: Option<u64> = read_persistent(env, &DataKey::ChargedPeriod(subscription_id));
+
+    sub.amount = plan_amount_at(env, &plan, now);
+    sub.interval_seconds = plan.interval_seconds;
+    sub.cadence = BillingCadence::Fixed;
+    sub.usage_enabled = plan.usage_enabled;
+    sub.plan_id = Some(new_plan_id);
+    // The new plan's price already reflects its changes in force.
+    sub.price_scheduled_at = None;
+    sub.billing_anchor = anchor;
+    sub.anchor_period = first_period;
+
+    let charged = if trialing {
+        0
+    } else {
+        // A trial that has run out ends with the new full-price period.
+        end_trial(&mut sub);
+        let charged = amount_due(&sub, now);
+        roll_forward(&mut sub, now);
+        sub.last_payment_timestamp = now;
+        charged
+    };
+    let completed = !trialing && record_cycle(&mut sub)?;
+    let net_amount = safe_sub(charged, credit)?;
+
+    if net_amount > 0 {
+        sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, net_amount)
+            .map_err(|_| Error::InsufficientBalance)?;
+        sub.total_charged = safe_add_balance(sub.total_charged, net_amount)?;
+        decrease_prepaid_liabilities(env, net_amount)?;
+        credit_merchant_balance(env, &sub.merchant, net_amount)?;
+    } else if net_amount < 0 {
+        // Only what the merchant has not withdrawn can be returned.
+        let refund = (-net_amount).min(get_merchant_balance(env, &sub.merchant));
+        credit = safe_add(charged, refund)?;
+        if refund > 0 {
+            debit_merchant_balance(env, &sub.merchant, refund)?;
+            sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, refund)?;
+            sub.total_charged = safe_sub_balance(sub.total_charged, refund)?;
+            increase_prepaid_liabilities(env, refund)?;
+        }
+    }
+    let net_amount = safe_sub(charged, credit)?;
+
+    if completed {
+        settle_pending_usage(env, subscription_id, &mut sub)?;
+    }
+    save_subscription(env, subscription_id, &sub);
+    env.storage()
+        .persistent()
+        .remove(&DataKey::SubPriceChange(subscription_id));
+    if let Some(paid_period) = paid_period.filter(|_| credit > 0) {
+        reduce_period_charge(env, subscription_id, paid_period, credit)?;
+    }
+    if !trialing {
+        // The first period of the new anchor is paid.
+        write_persistent(
+            env,
+            &DataKey::ChargedPeriod(subscription_id),
+            &sub.anchor_period,
+        );
+        record_period_charge(env, subscription_id, sub.anchor_period, charged)?;
+        write_persistent(
+            env,
+            &DataKey::LastCharge(subscription_id),
+            &LastCharge {
+                amount: charged,
+                period_start: now,
+            },
+        );
+    }
+
+    env.events().publish(
+        (Symbol::new(env, "plan_changed"), subscription_id),
+        PlanChangedEvent {
+            subscription_id,
+            old_plan_id,
+            new_plan_id,
+            old_amount,
+            new_amount: sub.amount,
+            credit,
+            charged,
+            net_amount,
+        },
+    );
+    if completed {
+        emit_completed(env, subscription_id, &sub);
+    }
+    Ok(())
+}
+
+/// Number for the first period of a new anchor: one past both the last charged
+/// period and the period containing `now` under the current anchor.
+fn next_period_number(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    now: u64,
+) -> Result<u64, Error> {
+    let charged = read_persistent::<u64>(env, &DataKey::ChargedPeriod(subscription_id));
+    match charged.max(period_at(sub, now)) {
+        Some(last) => last.checked_add(1).ok_or(Error::Overflow),
+        None => Ok(sub.anchor_period),
+    }
+}
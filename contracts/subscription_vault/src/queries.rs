@@ -4,14 +4,28 @@
 
 #![allow(dead_code)]
 
-use crate::types::{DataKey, Error, NextChargeInfo, Subscription, SubscriptionStatus};
-use soroban_sdk::{contracttype, Address, Env, Symbol, Vec};
-
+use crate::cancellation::apply_due_cancellation;
+use crate::charge_core::{amount_due, roll_forward};
+use crate::pause::apply_due_resume;
+use crate::pricing::{has_upcoming_price_change, price_for_period};
+use crate::safe_math::safe_add;
+use crate::schedule::next_charge_due;
+use crate::storage::{
+    load_subscription, merchant_subscription_at, merchant_subscription_count,
+    subscriber_subscription_at, subscriber_subscription_count,
+};
+use crate::term::beyond_term;
+use crate::types::{Error, NextChargeInfo, Subscription, SubscriptionStatus};
+use soroban_sdk::{contracttype, Address, Env, Vec};
+
+/// Loads a subscription, applying a due automatic resume and a scheduled
+/// cancellation whose period has ended.
 pub fn get_subscription(env: &Env, subscription_id: u32) -> Result<Subscription, Error> {
-    env.storage()
-        .instance()
-        .get(&subscription_id)
-        .ok_or(Error::NotFound)
+    let mut sub = load_subscription(env, subscription_id).ok_or(Error::NotFound)?;
+    let now = env.ledger().timestamp();
+    apply_due_resume(&mut sub, now);
+    apply_due_cancellation(&mut sub, now);
+    Ok(sub)
 }
 
 pub fn estimate_topup_for_intervals(
@@ -25,10 +39,30 @@ pub fn estimate_topup_for_intervals(
         return Ok(0);
     }
 
-    let intervals_i128: i128 = num_intervals.into();
-    let required = sub
+    // Walk the periods still affected by a trial, coupon or scheduled price
+    // change, then price the rest at the amount in force from then on.
+    let mut sim = sub.clone();
+    let mut required: i128 = 0;
+    let mut remaining = num_intervals;
+    while remaining > 0 {
+        let period_start = next_charge_due(&sim)?;
+        price_for_period(env, subscription_id, &mut sim, period_start);
+        if sim.trial_ends_at.is_none()
+            && sim.discount_cycles_left == 0
+            && !has_upcoming_price_change(env, subscription_id, &sim, period_start)
+        {
+            break;
+        }
+        required = safe_add(required, amount_due(&sim, period_start))?;
+        roll_forward(&mut sim, period_start);
+        sim.last_payment_timestamp = period_start;
+        remaining -= 1;
+    }
+    let intervals_i128: i128 = remaining.into();
+    let required = sim
         .amount
         .checked_mul(intervals_i128)
+        .and_then(|rest| rest.checked_add(required))
         .ok_or(Error::Overflow)?;
 
     let topup = required
@@ -53,28 +87,20 @@ pub fn get_subscriptions_by_merchant(
     start: u32,
     limit: u32,
 ) -> Vec<Subscription> {
-    let key = DataKey::MerchantSubs(merchant);
-    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
-
-    let len = ids.len();
+    let len = merchant_subscription_count(env, &merchant);
     if start >= len || limit == 0 {
         return Vec::new(env);
     }
 
-    let end = if start + limit > len {
-        len
-    } else {
-        start + limit
-    };
+    let end = start.saturating_add(limit).min(len);
 
     let mut result = Vec::new(env);
-    let mut i = start;
-    while i < end {
-        let sub_id = ids.get(i).unwrap();
-        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&sub_id) {
+    for i in start..end {
+        if let Some(sub) = merchant_subscription_at(env, &merchant, i)
+            .and_then(|id| get_subscription(env, id).ok())
+        {
             result.push_back(sub);
         }
-        i += 1;
     }
     result
 }
@@ -83,9 +109,17 @@ pub fn get_subscriptions_by_merchant(
 ///
 /// Useful for dashboards and pagination metadata.
 pub fn get_merchant_subscription_count(env: &Env, merchant: Address) -> u32 {
-    let key = DataKey::MerchantSubs(merchant);
-    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
-    ids.len()
+    merchant_subscription_count(env, &merchant)
+}
+
+/// Next charge info for a stored subscription, priced with any scheduled price
+/// change in force by the next charge.
+pub fn get_next_charge_info(env: &Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    if let Ok(due) = next_charge_due(&sub) {
+        price_for_period(env, subscription_id, &mut sub, due);
+    }
+    Ok(compute_next_charge_info(&sub))
 }
 
 /// Computes the estimated next charge timestamp for a subscription.
@@ -93,20 +127,26 @@ pub fn get_merchant_subscription_count(env: &Env, merchant: Address) -> u32 {
 /// This is a readonly helper that does not mutate contract state. It provides
 /// information for off-chain scheduling systems and UX displays.
 pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
-    let next_charge_timestamp = subscription
-        .last_payment_timestamp
-        .saturating_add(subscription.interval_seconds);
-
+    // Anchored, so a late previous charge does not move it. During a free trial
+    // this is the first paid charge, at the trial end.
+    let next_charge_timestamp = next_charge_due(subscription).unwrap_or(u64::MAX);
+
+    // No charge is expected once the fixed term is used up or a cancellation
+    // is scheduled for the end of the current period.
+    let in_term =
+        !beyond_term(subscription, next_charge_timestamp) && !subscription.cancel_at_period_end;
     let is_charge_expected = match subscription.status {
-        SubscriptionStatus::Active => true,
-        SubscriptionStatus::InsufficientBalance => true,
+        SubscriptionStatus::Active => in_term,
+        SubscriptionStatus::InsufficientBalance => in_term,
         SubscriptionStatus::Paused => false,
         SubscriptionStatus::Cancelled => false,
+        SubscriptionStatus::Completed => false,
     };
 
     NextChargeInfo {
         next_charge_timestamp,
         is_charge_expected,
+        amount: amount_due(subscription, next_charge_timestamp),
     }
 }
 
@@ -140,9 +180,9 @@ pub struct SubscriptionsPage {
 /// - `has_next`: True if there are more subscriptions after the last returned ID
 ///
 /// # Performance Notes
-/// - Time complexity: O(n) where n = total number of subscriptions in the contract
+/// - Reads the subscriber's own ID index, never the global subscription set.
+/// - Time complexity: O(log n + limit) where n = subscriptions owned by `subscriber`
 /// - Space complexity: O(limit)
-/// - On-chain storage usage is minimal (only subscription objects are stored)
 /// - Suitable for off-chain indexers and UI pagination
 ///
 /// # Pagination Example
@@ -166,52 +206,29 @@ pub fn list_subscriptions_by_subscriber(
         return Err(Error::NotFound);
     }
 
-    // Get the next_id counter to determine the range of valid subscription IDs
-    let next_id_key = Symbol::new(env, "next_id");
-    let next_id: u32 = env.storage().instance().get(&next_id_key).unwrap_or(0);
+    let len = subscriber_subscription_count(env, &subscriber);
 
-    let mut subscription_ids = Vec::new(env);
-    let mut count = 0u32;
-    let mut last_found_id = start_from_id;
-
-    // Iterate through all subscription IDs from start_from_id (inclusive) and filter by subscriber
-    for id in start_from_id..next_id {
-        match env.storage().instance().get::<u32, Subscription>(&id) {
-            Some(sub) => {
-                if sub.subscriber == subscriber {
-                    subscription_ids.push_back(id);
-                    count += 1;
-                    last_found_id = id;
-                    if count >= limit {
-                        break;
-                    }
-                }
-            }
-            None => {
-                // Subscription was deleted or ID skipped; continue to next
-            }
+    // The index is in ascending ID order: binary search for the first
+    // position whose ID is >= start_from_id.
+    let (mut lo, mut hi) = (0u32, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match subscriber_subscription_at(env, &subscriber, mid) {
+            Some(id) if id < start_from_id => lo = mid + 1,
+            _ => hi = mid,
         }
     }
 
-    // Determine if there are more subscriptions by checking beyond the last found
-    let has_next = if count >= limit {
-        // We hit the limit; check if there is at least one more subscriber match
-        let mut found_next = false;
-        for id in (last_found_id + 1)..next_id {
-            if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
-                if sub.subscriber == subscriber {
-                    found_next = true;
-                    break;
-                }
-            }
+    let end = lo.saturating_add(limit).min(len);
+    let mut subscription_ids = Vec::new(env);
+    for i in lo..end {
+        if let Some(id) = subscriber_subscription_at(env, &subscriber, i) {
+            subscription_ids.push_back(id);
         }
-        found_next
-    } else {
-        false
-    };
+    }
 
     Ok(SubscriptionsPage {
         subscription_ids,
-        has_next,
+        has_next: end < len,
     })
 }
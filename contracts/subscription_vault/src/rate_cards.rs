@@ -0,0 +1,167 @@
+//! Usage rate cards: pricing metered units on-chain.
+//!
+//! **PRs that only change how usage units are priced should edit this file only.**
+//!
+//! A merchant attaches a [`RateCard`] to a plan. Meters then report units with
+//! `report_usage_units` instead of token amounts, and the contract prices them
+//! from the period-to-date total, so anyone can check a usage charge against
+//! the rate card and the stored [`UnitUsage`].
+//!
+//! The first `included_units` of each billing period are free. The billable
+//! rest is priced by the tiers: `Graduated` prices each unit by the tier it
+//! falls in, `Volume` prices all units by the tier the total falls in. Units
+//! beyond the last tier cost `unit_price`. Each report charges the period's
+//! total cost minus what the period was already charged. Under volume pricing
+//! a cheaper tier can make that difference negative: the difference is then
+//! credited back (see [`credit_usage`]), so a period never pays more than its
+//! total priced at the tier it ends in. A credit the merchant's balance cannot
+//! cover stays charged.
+//!
+//! The rate card in effect at a period's first report is stored with the
+//! period's usage and prices the rest of that period, so a merchant's rate card
+//! change never reprices units already used and applies from the next period.
+
+use crate::charge_core::{credit_usage, debit_usage};
+use crate::meters::require_meter;
+use crate::plans::get_owned_plan;
+use crate::queries::get_subscription;
+use crate::safe_math::{safe_add, safe_sub};
+use crate::schedule::period_at;
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error, RateCard, SubscriptionStatus, TierMode, UnitUsage};
+use soroban_sdk::{Address, Env, Symbol};
+
+fn validate_rate_card(card: &RateCard) -> Result<(), Error> {
+    if card.unit_price < 0 {
+        return Err(Error::InvalidRateCard);
+    }
+    let mut prev = 0u64;
+    for tier in card.tiers.iter() {
+        if tier.unit_price < 0 || tier.up_to <= prev {
+            return Err(Error::InvalidRateCard);
+        }
+        prev = tier.up_to;
+    }
+    Ok(())
+}
+
+pub fn do_set_plan_rate_card(
+    env: &Env,
+    merchant: Address,
+    plan_id: u32,
+    card: RateCard,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    get_owned_plan(env, &merchant, plan_id)?;
+    validate_rate_card(&card)?;
+    write_persistent(env, &DataKey::PlanRateCard(plan_id), &card);
+    env.events().publish(
+        (Symbol::new(env, "rate_card_set"), merchant),
+        (plan_id, card),
+    );
+    Ok(())
+}
+
+pub fn get_plan_rate_card(env: &Env, plan_id: u32) -> Option<RateCard> {
+    read_persistent(env, &DataKey::PlanRateCard(plan_id))
+}
+
+fn units_cost(units: u64, unit_price: i128) -> Result<i128, Error> {
+    i128::from(units)
+        .checked_mul(unit_price)
+        .ok_or(Error::Overflow)
+}
+
+/// Cost of `units` billable units (after included units) under `card`.
+pub fn price_units(card: &RateCard, units: u64) -> Result<i128, Error> {
+    match card.mode {
+        TierMode::Volume => {
+            let price = card
+                .tiers
+                .iter()
+                .find(|tier| units <= tier.up_to)
+                .map_or(card.unit_price, |tier| tier.unit_price);
+            units_cost(units, price)
+        }
+        TierMode::Graduated => {
+            let mut cost = 0i128;
+            let mut priced = 0u64;
+            for tier in card.tiers.iter() {
+                if units <= priced {
+                    break;
+                }
+                let upper = units.min(tier.up_to);
+                cost = safe_add(cost, units_cost(upper - priced, tier.unit_price)?)?;
+                priced = upper;
+            }
+            if units > priced {
+                cost = safe_add(cost, units_cost(units - priced, card.unit_price)?)?;
+            }
+            Ok(cost)
+        }
+    }
+}
+
+fn period_usage(env: &Env, subscription_id: u32, period: Option<u64>) -> Option<UnitUsage> {
+    read_persistent::<UnitUsage>(env, &DataKey::UnitUsage(subscription_id))
+        .filter(|usage| usage.period == period)
+}
+
+/// Units reported so far in the subscription's current billing period;
+/// `None` until the period's first report.
+pub fn get_unit_usage(env: &Env, subscription_id: u32) -> Result<Option<UnitUsage>, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let period = period_at(&sub, env.ledger().timestamp());
+    Ok(period_usage(env, subscription_id, period))
+}
+
+/// Prices `units` reported by `meter` and debits them like `charge_usage`.
+/// Returns the amount charged: 0 while within included units, negative when
+/// volume pricing credits usage back.
+pub fn do_report_usage_units(
+    env: &Env,
+    subscription_id: u32,
+    meter: Address,
+    units: u64,
+) -> Result<i128, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    require_meter(env, &sub, &meter)?;
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+    if !sub.usage_enabled {
+        return Err(Error::UsageNotEnabled);
+    }
+    if units == 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let period = period_at(&sub, env.ledger().timestamp());
+    let mut usage = match period_usage(env, subscription_id, period) {
+        Some(usage) => usage,
+        None => UnitUsage {
+            period,
+            units: 0,
+            amount: 0,
+            card: sub
+                .plan_id
+                .and_then(|plan_id| get_plan_rate_card(env, plan_id))
+                .ok_or(Error::NoRateCard)?,
+        },
+    };
+    usage.units = usage.units.checked_add(units).ok_or(Error::Overflow)?;
+    let billable = usage.units.saturating_sub(usage.card.included_units);
+    let mut amount = safe_sub(price_units(&usage.card, billable)?, usage.amount)?;
+    if amount > 0 {
+        debit_usage(env, subscription_id, sub, amount)?;
+    } else if amount < 0 {
+        amount = -credit_usage(env, subscription_id, sub, -amount)?;
+    }
+    usage.amount = safe_add(usage.amount, amount)?;
+    write_persistent(env, &DataKey::UnitUsage(subscription_id), &usage);
+    env.events().publish(
+        (Symbol::new(env, "usage_units"), subscription_id),
+        (units, usage.units, amount),
+    );
+    Ok(amount)
+}
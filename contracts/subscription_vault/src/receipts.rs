@@ -0,0 +1,60 @@
+//! Usage receipts signed off-chain by a meter key and relayed by anyone.
+//!
+//! **PRs that only change receipt verification should edit this file only.**
+//!
+//! A meter running without a Stellar account signs a [`UsageReceipt`] with an
+//! ed25519 key that the merchant registered (`register_meter_key`). Any account
+//! may submit it. The signed message is the XDR encoding of the tuple
+//! `(contract address, receipt)`, so a receipt cannot be replayed against
+//! another deployment. Nonces must increase per key: only the last accepted
+//! nonce is stored (one entry per key), and a receipt whose nonce is not above
+//! it is a replay, so a meter's receipts are submitted in order. A receipt
+//! must be submitted before `expires_at` and during the billing period it
+//! names. Before the first billing period starts no period matches, so
+//! receipts are rejected until then. An accepted receipt debits like
+//! `charge_usage`. An invalid signature aborts the transaction.
+
+use crate::charge_core::debit_usage;
+use crate::meters::is_meter_key_for;
+use crate::queries::get_subscription;
+use crate::schedule::period_at;
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error, UsageReceipt};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{BytesN, Env, Symbol};
+
+pub fn do_submit_usage_receipt(
+    env: &Env,
+    receipt: UsageReceipt,
+    public_key: BytesN<32>,
+    signature: BytesN<64>,
+) -> Result<(), Error> {
+    let sub = get_subscription(env, receipt.subscription_id)?;
+    let now = env.ledger().timestamp();
+    if now > receipt.expires_at {
+        return Err(Error::ReceiptExpired);
+    }
+    if !is_meter_key_for(env, &sub, &public_key) {
+        return Err(Error::Unauthorized);
+    }
+    let nonce_key = DataKey::ReceiptNonce(public_key.clone());
+    if read_persistent::<u64>(env, &nonce_key).is_some_and(|last| receipt.nonce <= last) {
+        return Err(Error::Replay);
+    }
+
+    let message = (env.current_contract_address(), receipt.clone()).to_xdr(env);
+    env.crypto()
+        .ed25519_verify(&public_key, &message, &signature);
+
+    if period_at(&sub, now) != Some(receipt.period) {
+        return Err(Error::ReceiptPeriodMismatch);
+    }
+
+    write_persistent(env, &nonce_key, &receipt.nonce);
+    debit_usage(env, receipt.subscription_id, sub, receipt.usage_amount)?;
+    env.events().publish(
+        (Symbol::new(env, "usage_receipt"), receipt.subscription_id),
+        (public_key, receipt.nonce, receipt.usage_amount),
+    );
+    Ok(())
+}
@@ -0,0 +1,213 @@
+//! Merchant-initiated refunds.
+//!
+//! **PRs that only change how merchants refund charges should edit this file only.**
+//!
+//! A refund is debited from the merchant's claimable balance and either
+//! credited back to the subscription's `prepaid_balance` or transferred to the
+//! subscriber's wallet. Each refund names the billing period whose charge it
+//! refunds and a merchant-defined reason code. Every charge is recorded against
+//! its period: interval charges against the period they pay for, usage and
+//! one-off charges against the period they are made in; a refund must name a recorded period and cannot exceed what
+//! is left of that period's charge (`RefundPeriodNotCharged` for a period that
+//! was skipped or never reached). Only the last `REFUNDABLE_PERIODS` charged
+//! periods are kept, in one bounded entry per subscription; older periods can
+//! no longer be refunded. Refunds are also capped per subscription:
+//! `total_refunded` can never exceed `total_charged`.
+//!
+//! A merchant may also opt in to prorated refunds on cancellation: cancelling
+//! then returns the unused part of the last charged period to the prepaid
+//! balance, limited by the cap and by what the merchant has not withdrawn.
+
+use crate::accounting::increase_prepaid_liabilities;
+use crate::admin::get_token;
+use crate::merchant::{debit_merchant_balance, get_merchant_balance};
+use crate::proration::unused_credit;
+use crate::queries::get_subscription;
+use crate::safe_math::{safe_add_balance, safe_sub, safe_sub_balance};
+use crate::schedule::period_at;
+use crate::storage::{read_persistent, save_subscription, write_persistent};
+use crate::types::{DataKey, Error, RefundDestination, RefundIssuedEvent, Subscription};
+use soroban_sdk::{Address, Env, Map, Symbol};
+
+pub fn do_refund(
+    env: &Env,
+    subscription_id: u32,
+    amount: i128,
+    period: u64,
+    reason: u32,
+    destination: RefundDestination,
+) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    sub.merchant.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let period_refundable =
+        get_period_charge(env, subscription_id, period).ok_or(Error::RefundPeriodNotCharged)?;
+    let refundable = safe_sub_balance(sub.total_charged, sub.total_refunded)?;
+    if amount > refundable || amount > period_refundable {
+        return Err(Error::RefundExceedsCharged);
+    }
+
+    // Debit before paying out (checks-effects-interactions).
+    debit_merchant_balance(env, &sub.merchant, amount)?;
+    reduce_period_charge(env, subscription_id, period, amount)?;
+    sub.total_refunded = safe_add_balance(sub.total_refunded, amount)?;
+    match destination {
+        RefundDestination::PrepaidBalance => {
+            sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, amount)?;
+            increase_prepaid_liabilities(env, amount)?;
+            save_subscription(env, subscription_id, &sub);
+        }
+        RefundDestination::Subscriber => {
+            save_subscription(env, subscription_id, &sub);
+            let token_client = soroban_sdk::token::Client::new(env, &get_token(env)?);
+            token_client.transfer(&env.current_contract_address(), &sub.subscriber, &amount);
+        }
+    }
+
+    env.events().publish(
+        (Symbol::new(env, "refunded"), subscription_id),
+        RefundIssuedEvent {
+            subscription_id,
+            merchant: sub.merchant,
+            amount,
+            period,
+            reason,
+            destination,
+            total_refunded: sub.total_refunded,
+        },
+    );
+    Ok(())
+}
+
+/// Number of most recently charged periods that can still be refunded.
+pub const REFUNDABLE_PERIODS: u32 = 12;
+
+fn period_charges(env: &Env, subscription_id: u32) -> Map<u64, i128> {
+    read_persistent(env, &DataKey::PeriodCharges(subscription_id)).unwrap_or(Map::new(env))
+}
+
+/// What is left to refund of the charge for `period`; `None` if the period
+/// was never charged or has left the refund window.
+pub fn get_period_charge(env: &Env, subscription_id: u32, period: u64) -> Option<i128> {
+    period_charges(env, subscription_id).get(period)
+}
+
+/// Records `amount` as charged for `period`. Once more than
+/// [`REFUNDABLE_PERIODS`] periods are recorded, the oldest is dropped.
+pub fn record_period_charge(
+    env: &Env,
+    subscription_id: u32,
+    period: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    let mut charges = period_charges(env, subscription_id);
+    let recorded = charges.get(period).unwrap_or(0);
+    charges.set(period, safe_add_balance(recorded, amount)?);
+    if charges.len() > REFUNDABLE_PERIODS {
+        if let Some(oldest) = charges.keys().first() {
+            charges.remove(oldest);
+        }
+    }
+    write_persistent(env, &DataKey::PeriodCharges(subscription_id), &charges);
+    Ok(())
+}
+
+/// Records an off-cycle charge made at `now` (usage, a one-off charge, settled
+/// postpaid usage) against the period containing it. Charges made before the
+/// first period count towards the first period.
+pub fn record_charge_at(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    now: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    let period = period_at(sub, now).unwrap_or(sub.anchor_period);
+    record_period_charge(env, subscription_id, period, amount)
+}
+
+/// Takes `amount` returned at `now` off the record of the period containing
+/// it, the counterpart of [`record_charge_at`].
+pub fn reduce_charge_at(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    now: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    let period = period_at(sub, now).unwrap_or(sub.anchor_period);
+    reduce_period_charge(env, subscription_id, period, amount)
+}
+
+/// Takes `amount` returned to the subscriber off the record for `period`,
+/// stopping at zero.
+pub fn reduce_period_charge(
+    env: &Env,
+    subscription_id: u32,
+    period: u64,
+    amount: i128,
+) -> Result<(), Error> {
+    let mut charges = period_charges(env, subscription_id);
+    if let Some(recorded) = charges.get(period) {
+        charges.set(period, safe_sub(recorded, amount)?.max(0));
+        write_persistent(env, &DataKey::PeriodCharges(subscription_id), &charges);
+    }
+    Ok(())
+}
+
+pub fn do_set_prorated_cancel_refunds(
+    env: &Env,
+    merchant: Address,
+    enabled: bool,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    write_persistent(
+        env,
+        &DataKey::MerchantCancelRefund(merchant.clone()),
+        &enabled,
+    );
+    env.events()
+        .publish((Symbol::new(env, "cancel_refund_set"), merchant), enabled);
+    Ok(())
+}
+
+/// Whether the merchant refunds unused time on cancellation; off unless set.
+pub fn get_prorated_cancel_refunds(env: &Env, merchant: &Address) -> bool {
+    read_persistent(env, &DataKey::MerchantCancelRefund(merchant.clone())).unwrap_or(false)
+}
+
+/// Moves the unused part of the last charged period from the merchant's
+/// balance back into `prepaid_balance` if the merchant's policy asks for it,
+/// and returns the amount. A paused subscription is refunded as of the pause.
+/// The caller saves `sub`.
+pub fn refund_unused_period(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    now: u64,
+) -> Result<i128, Error> {
+    if !get_prorated_cancel_refunds(env, &sub.merchant) {
+        return Ok(0);
+    }
+    let Some(period) = read_persistent::<u64>(env, &DataKey::ChargedPeriod(subscription_id)) else {
+        return Ok(0);
+    };
+    let as_of = sub.paused_at.unwrap_or(now);
+    let refundable = safe_sub_balance(sub.total_charged, sub.total_refunded)?;
+    let amount = unused_credit(env, subscription_id, sub, as_of)?
+        .min(refundable)
+        .min(get_period_charge(env, subscription_id, period).unwrap_or(0))
+        .min(get_merchant_balance(env, &sub.merchant));
+    if amount <= 0 {
+        return Ok(0);
+    }
+
+    debit_merchant_balance(env, &sub.merchant, amount)?;
+    reduce_period_charge(env, subscription_id, period, amount)?;
+    sub.total_refunded = safe_add_balance(sub.total_refunded, amount)?;
+    sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, amount)?;
+    increase_prepaid_liabilities(env, amount)?;
+    Ok(amount)
+}
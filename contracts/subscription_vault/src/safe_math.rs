@@ -190,3 +190,42 @@ pub fn safe_sub_balance(balance: i128, amount: i128) -> Result<i128, Error> {
         Ok(result)
     }
 }
+
+/// Prorates `amount` by `numerator / denominator`, rounding down.
+///
+/// Used to price a partial billing period, e.g. the unused seconds of the
+/// current interval. The multiplication is checked before the division so no
+/// precision is lost to an early divide.
+///
+/// # Arguments
+///
+/// * `amount` - Full-period amount (must be non-negative)
+/// * `numerator` - Portion of the period, e.g. unused seconds
+/// * `denominator` - Length of the full period, e.g. `interval_seconds`
+///
+/// # Returns
+///
+/// * `Ok(i128)` - `amount * numerator / denominator`, rounded down
+/// * `Err(Error::Underflow)` - If `amount` is negative
+/// * `Err(Error::Overflow)` - If `amount * numerator` overflows or `denominator` is zero
+///
+/// # Examples
+///
+/// ```
+/// use subscription_vault::safe_math::safe_prorate;
+/// use subscription_vault::Error;
+///
+/// assert_eq!(safe_prorate(30_000_000, 10, 30), Ok(10_000_000));
+/// assert_eq!(safe_prorate(100, 1, 3), Ok(33));
+/// assert_eq!(safe_prorate(100, 1, 0), Err(Error::Overflow));
+/// ```
+pub fn safe_prorate(amount: i128, numerator: u64, denominator: u64) -> Result<i128, Error> {
+    validate_non_negative(amount)?;
+    if denominator == 0 {
+        return Err(Error::Overflow);
+    }
+    amount
+        .checked_mul(numerator as i128)
+        .map(|scaled| scaled / denominator as i128)
+        .ok_or(Error::Overflow)
+}
@@ -0,0 +1,115 @@
+//! Billing schedule: anchored billing periods and next-due computation.
+//!
+//! **PRs that only change when charges fall due should edit this file only.**
+//!
+//! Every subscription has a `billing_anchor`: the start of billing period
+//! `anchor_period` and the time its first charge falls due. `anchor_period` is 0
+//! until a plan change re-anchors billing; it then continues the numbering, so
+//! period numbers only ever increase. How later periods follow depends on the
+//! subscription's [`BillingCadence`] (with `k = n - anchor_period`):
+//! - `Fixed`: period `n` starts at `billing_anchor + k * interval_seconds`;
+//! - `MonthlyOnDay` / `YearlyOnDate`: period `n` starts `k` months (or years)
+//!   after the anchor on the configured day, clamped to the end of shorter
+//!   months (see [`crate::calendar`]).
+//!
+//! Because due times are derived from the anchor rather than from when the last
+//! charge happened to run, a late charge does not push the schedule back.
+//!
+//! A charge always bills the period containing the current time. Periods that
+//! elapsed entirely without a charge are skipped, and replay protection is keyed
+//! on the anchored period number.
+
+use crate::calendar::{days_in_month, month_day_at, month_index, SECONDS_PER_DAY};
+use crate::types::{BillingCadence, Error, Subscription};
+
+/// Months per period and billing day of a calendar cadence; `None` for `Fixed`.
+fn calendar_step(cadence: &BillingCadence) -> Option<(i64, u32)> {
+    match cadence {
+        BillingCadence::Fixed => None,
+        BillingCadence::MonthlyOnDay(day) => Some((1, *day)),
+        BillingCadence::YearlyOnDate(_, day) => Some((12, *day)),
+    }
+}
+
+/// Rejects calendar cadences naming a day or month that never occurs.
+pub fn validate_cadence(cadence: &BillingCadence) -> Result<(), Error> {
+    let valid = match cadence {
+        BillingCadence::Fixed => true,
+        BillingCadence::MonthlyOnDay(day) => (1..=31).contains(day),
+        // Checked against a leap year so 29 February is allowed.
+        BillingCadence::YearlyOnDate(month, day) => {
+            (1..=12).contains(month) && *day >= 1 && *day <= days_in_month(2000, *month)
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInterval)
+    }
+}
+
+/// First billing date of a calendar `cadence` strictly after `now`, at midnight UTC.
+pub fn first_calendar_due(cadence: &BillingCadence, now: u64) -> Result<u64, Error> {
+    let (step, day) = calendar_step(cadence).ok_or(Error::InvalidInterval)?;
+    let mut month = match cadence {
+        BillingCadence::YearlyOnDate(m, _) => {
+            let this_year = month_index(now).div_euclid(12);
+            this_year * 12 + *m as i64 - 1
+        }
+        _ => month_index(now),
+    };
+    loop {
+        let due = month_day_at(month, day, 0).ok_or(Error::Overflow)?;
+        if due > now {
+            return Ok(due);
+        }
+        month += step;
+    }
+}
+
+/// Start of billing period `period`. Fails for periods before `anchor_period`.
+pub fn period_start(sub: &Subscription, period: u64) -> Result<u64, Error> {
+    let period = period
+        .checked_sub(sub.anchor_period)
+        .ok_or(Error::Underflow)?;
+    match calendar_step(&sub.cadence) {
+        None => period
+            .checked_mul(sub.interval_seconds)
+            .and_then(|offset| sub.billing_anchor.checked_add(offset))
+            .ok_or(Error::Overflow),
+        Some((step, day)) => i64::try_from(period)
+            .ok()
+            .and_then(|p| p.checked_mul(step))
+            .and_then(|months| month_index(sub.billing_anchor).checked_add(months))
+            .and_then(|month| month_day_at(month, day, sub.billing_anchor % SECONDS_PER_DAY))
+            .ok_or(Error::Overflow),
+    }
+}
+
+/// Billing period containing `timestamp`, or `None` before the anchor.
+pub fn period_at(sub: &Subscription, timestamp: u64) -> Option<u64> {
+    let elapsed = timestamp.checked_sub(sub.billing_anchor)?;
+    let since_anchor = match calendar_step(&sub.cadence) {
+        None if sub.interval_seconds == 0 => 0,
+        None => elapsed / sub.interval_seconds,
+        Some((step, _)) => {
+            let months = month_index(timestamp) - month_index(sub.billing_anchor);
+            let since_anchor = (months / step) as u64;
+            // Early in its month, `timestamp` still belongs to the previous period.
+            match period_start(sub, sub.anchor_period.saturating_add(since_anchor)) {
+                Ok(start) if start <= timestamp => since_anchor,
+                _ => since_anchor.saturating_sub(1),
+            }
+        }
+    };
+    sub.anchor_period.checked_add(since_anchor)
+}
+
+/// When the next charge falls due: the anchor if nothing has been charged yet,
+/// otherwise the start of the period after the one last paid.
+pub fn next_charge_due(sub: &Subscription) -> Result<u64, Error> {
+    match period_at(sub, sub.last_payment_timestamp) {
+        None => Ok(sub.billing_anchor),
+        Some(paid) => period_start(sub, paid.checked_add(1).ok_or(Error::Overflow)?),
+    }
+}
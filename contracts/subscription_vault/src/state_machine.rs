@@ -14,11 +14,14 @@ use crate::types::{Error, SubscriptionStatus};
 /// | Active            | Paused              | Yes     |
 /// | Active            | Cancelled           | Yes     |
 /// | Active            | InsufficientBalance | Yes     |
+/// | Active            | Completed           | Yes     |
 /// | Paused            | Active              | Yes     |
 /// | Paused            | Cancelled           | Yes     |
 /// | InsufficientBalance | Active            | Yes     |
 /// | InsufficientBalance | Cancelled         | Yes     |
+/// | InsufficientBalance | Completed         | Yes     |
 /// | Cancelled         | *any*               | No      |
+/// | Completed         | *any*               | No      |
 /// | *any*             | Same status         | Yes (idempotent) |
 ///
 /// # Arguments
@@ -42,6 +45,7 @@ pub fn validate_status_transition(
             SubscriptionStatus::Paused
                 | SubscriptionStatus::Cancelled
                 | SubscriptionStatus::InsufficientBalance
+                | SubscriptionStatus::Completed
         ),
         SubscriptionStatus::Paused => {
             matches!(
@@ -49,11 +53,13 @@ pub fn validate_status_transition(
                 SubscriptionStatus::Active | SubscriptionStatus::Cancelled
             )
         }
-        SubscriptionStatus::Cancelled => false,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed => false,
         SubscriptionStatus::InsufficientBalance => {
             matches!(
                 to,
-                SubscriptionStatus::Active | SubscriptionStatus::Cancelled
+                SubscriptionStatus::Active
+                    | SubscriptionStatus::Cancelled
+                    | SubscriptionStatus::Completed
             )
         }
     };
@@ -74,12 +80,15 @@ pub fn get_allowed_transitions(status: &SubscriptionStatus) -> &'static [Subscri
             SubscriptionStatus::Paused,
             SubscriptionStatus::Cancelled,
             SubscriptionStatus::InsufficientBalance,
+            SubscriptionStatus::Completed,
         ],
         SubscriptionStatus::Paused => &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled],
-        SubscriptionStatus::Cancelled => &[],
-        SubscriptionStatus::InsufficientBalance => {
-            &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled]
-        }
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed => &[],
+        SubscriptionStatus::InsufficientBalance => &[
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Cancelled,
+            SubscriptionStatus::Completed,
+        ],
     }
 }
 
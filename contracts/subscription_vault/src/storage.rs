@@ -0,0 +1,133 @@
+//! Storage layout and TTL management.
+//!
+//! **PRs that only change where or how long entries are stored should edit this file only.**
+//!
+//! Contract-wide configuration (token, admin, counters, liability totals) lives in
+//! instance storage. Everything that grows with usage — subscriptions, their charge
+//! metadata, per-account indices and merchant balances — lives in persistent storage
+//! under its own [`DataKey`], so the instance entry stays small no matter how many
+//! subscriptions exist.
+//!
+//! Persistent entries are extended whenever they are read or written. Long-idle
+//! subscriptions can be kept alive by anyone via
+//! [`crate::SubscriptionVault::extend_subscription_ttl`].
+
+use crate::types::{DataKey, Error, Subscription};
+use soroban_sdk::{Address, Env, IntoVal, TryFromVal, Val};
+
+/// Ledgers per day at ~5s close time.
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Instance storage is bumped to this many ledgers on touch.
+pub const INSTANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+/// Instance storage is bumped once its TTL drops below this.
+pub const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+/// Persistent entries are bumped to this many ledgers on touch.
+pub const PERSISTENT_BUMP_AMOUNT: u32 = 90 * DAY_IN_LEDGERS;
+/// Persistent entries are bumped once their TTL drops below this.
+pub const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - 7 * DAY_IN_LEDGERS;
+
+pub fn extend_instance_ttl(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+fn extend_persistent_ttl(env: &Env, key: &DataKey) {
+    env.storage().persistent().extend_ttl(
+        key,
+        PERSISTENT_LIFETIME_THRESHOLD,
+        PERSISTENT_BUMP_AMOUNT,
+    );
+}
+
+/// Reads a persistent entry, extending its TTL if it exists.
+pub fn read_persistent<V: TryFromVal<Env, Val>>(env: &Env, key: &DataKey) -> Option<V> {
+    let value = env.storage().persistent().get::<DataKey, V>(key);
+    if value.is_some() {
+        extend_persistent_ttl(env, key);
+    }
+    value
+}
+
+/// Writes a persistent entry and extends its TTL.
+pub fn write_persistent<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+    env.storage().persistent().set(key, value);
+    extend_persistent_ttl(env, key);
+}
+
+pub fn load_subscription(env: &Env, subscription_id: u32) -> Option<Subscription> {
+    extend_instance_ttl(env);
+    read_persistent(env, &DataKey::Subscription(subscription_id))
+}
+
+pub fn save_subscription(env: &Env, subscription_id: u32, sub: &Subscription) {
+    extend_instance_ttl(env);
+    write_persistent(env, &DataKey::Subscription(subscription_id), sub);
+}
+
+/// Extends the TTL of a subscription and all of its per-subscription metadata.
+///
+/// Callable by anyone (the caller pays the rent); there is no state change
+/// other than the extended lifetimes.
+pub fn extend_subscription_ttl(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    let key = DataKey::Subscription(subscription_id);
+    if !env.storage().persistent().has(&key) {
+        return Err(Error::NotFound);
+    }
+    extend_instance_ttl(env);
+    extend_persistent_ttl(env, &key);
+    for meta in [
+        DataKey::ChargedPeriod(subscription_id),
+        DataKey::IdemKey(subscription_id),
+        DataKey::SubPriceChange(subscription_id),
+        DataKey::LastCharge(subscription_id),
+        DataKey::OneOffLimits(subscription_id),
+        DataKey::OneOffSpend(subscription_id),
+        DataKey::UsageCaps(subscription_id),
+        DataKey::UsageSpend(subscription_id),
+        DataKey::UnitUsage(subscription_id),
+        DataKey::PeriodCharges(subscription_id),
+    ] {
+        if env.storage().persistent().has(&meta) {
+            extend_persistent_ttl(env, &meta);
+        }
+    }
+    Ok(())
+}
+
+// ── Append-only ID indices ───────────────────────────────────────────────────
+//
+// An index is a count entry plus one entry per position, so appending or reading
+// one page never loads the whole list.
+
+pub fn push_merchant_subscription(env: &Env, merchant: &Address, id: u32) {
+    let count_key = DataKey::MerchantSubCount(merchant.clone());
+    let len: u32 = read_persistent(env, &count_key).unwrap_or(0);
+    write_persistent(env, &DataKey::MerchantSubAt(merchant.clone(), len), &id);
+    write_persistent(env, &count_key, &(len + 1));
+}
+
+pub fn push_subscriber_subscription(env: &Env, subscriber: &Address, id: u32) {
+    let count_key = DataKey::SubscriberSubCount(subscriber.clone());
+    let len: u32 = read_persistent(env, &count_key).unwrap_or(0);
+    write_persistent(env, &DataKey::SubscriberSubAt(subscriber.clone(), len), &id);
+    write_persistent(env, &count_key, &(len + 1));
+}
+
+pub fn merchant_subscription_count(env: &Env, merchant: &Address) -> u32 {
+    read_persistent(env, &DataKey::MerchantSubCount(merchant.clone())).unwrap_or(0)
+}
+
+pub fn merchant_subscription_at(env: &Env, merchant: &Address, index: u32) -> Option<u32> {
+    read_persistent(env, &DataKey::MerchantSubAt(merchant.clone(), index))
+}
+
+pub fn subscriber_subscription_count(env: &Env, subscriber: &Address) -> u32 {
+    read_persistent(env, &DataKey::SubscriberSubCount(subscriber.clone())).unwrap_or(0)
+}
+
+pub fn subscriber_subscription_at(env: &Env, subscriber: &Address, index: u32) -> Option<u32> {
+    read_persistent(env, &DataKey::SubscriberSubAt(subscriber.clone(), index))
+}
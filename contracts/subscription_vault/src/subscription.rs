@@ -1,12 +1,27 @@
-//! Subscription lifecycle: create, deposit.
+//! Subscription lifecycle: create (custom or from a plan), deposit, cancel, pause, resume.
 //!
 //! **PRs that only change subscription lifecycle or billing should edit this file only.**
 
+use crate::accounting::{decrease_prepaid_liabilities, increase_prepaid_liabilities};
+use crate::auth_policy::{require_lifecycle_auth, LifecycleAction};
+use crate::charge_core::reactivate_after_deposit;
+use crate::coupons::redeem_coupon;
+use crate::pause::{do_pause_until, resume_billing_clock};
+use crate::plans::get_plan;
+use crate::postpaid::settle_pending_usage;
+use crate::pricing::plan_amount_at;
 use crate::queries::get_subscription;
+use crate::refunds::refund_unused_period;
 use crate::safe_math::{safe_add_balance, validate_non_negative};
+use crate::schedule::{first_calendar_due, validate_cadence};
 use crate::state_machine::validate_status_transition;
-use crate::types::{DataKey, Error, Subscription, SubscriptionStatus};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::storage::{push_merchant_subscription, push_subscriber_subscription, save_subscription};
+use crate::term::validate_term;
+use crate::trials::{first_charge_at, trial_terms};
+use crate::types::{
+    BillingCadence, Discount, Error, Subscription, SubscriptionCancelledEvent, SubscriptionStatus,
+};
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
 
 pub fn next_id(env: &Env) -> u32 {
     let key = Symbol::new(env, "next_id");
@@ -15,6 +30,13 @@ pub fn next_id(env: &Env) -> u32 {
     id
 }
 
+/// Creates a custom subscription whose terms are agreed by both parties.
+///
+/// Both the subscriber and the merchant must authorize, so a subscriber can
+/// never invent a price on their own. Use [`do_create_subscription_from_plan`]
+/// to subscribe to a merchant's published plan with subscriber auth only.
+///
+/// `coupon` is redeemed against the merchant's coupon registry (see [`crate::coupons`]).
 pub fn do_create_subscription(
     env: &Env,
     subscriber: Address,
@@ -22,27 +44,248 @@ pub fn do_create_subscription(
     amount: i128,
     interval_seconds: u64,
     usage_enabled: bool,
+    coupon: Option<Symbol>,
 ) -> Result<u32, Error> {
+    let sub = custom_subscription(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        BillingCadence::Fixed,
+        usage_enabled,
+        0,
+        None,
+    )?;
+    store_new_subscription(env, sub, coupon)
+}
+
+/// Creates a custom subscription that starts with a trial of `trial_seconds`,
+/// billed at `trial_amount` per interval (free when `None`). See [`crate::trials`].
+#[allow(clippy::too_many_arguments)]
+pub fn do_create_subscription_with_trial(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    trial_seconds: u64,
+    trial_amount: Option<i128>,
+) -> Result<u32, Error> {
+    let sub = custom_subscription(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        BillingCadence::Fixed,
+        usage_enabled,
+        trial_seconds,
+        trial_amount,
+    )?;
+    store_new_subscription(env, sub, None)
+}
+
+/// Creates a custom subscription that completes after `max_cycles` charges
+/// and/or once billing would reach `ends_at`. See [`crate::term`].
+#[allow(clippy::too_many_arguments)]
+pub fn do_create_fixed_term_subscription(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    max_cycles: Option<u32>,
+    ends_at: Option<u64>,
+) -> Result<u32, Error> {
+    validate_term(max_cycles, ends_at, env.ledger().timestamp())?;
+    let mut sub = custom_subscription(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        BillingCadence::Fixed,
+        usage_enabled,
+        0,
+        None,
+    )?;
+    sub.max_cycles = max_cycles;
+    sub.ends_at = ends_at;
+    store_new_subscription(env, sub, None)
+}
+
+/// Creates a custom subscription billed on calendar dates, e.g. the 15th of
+/// every month. The first charge falls due on the first such date after now.
+pub fn do_create_calendar_subscription(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    cadence: BillingCadence,
+    usage_enabled: bool,
+    coupon: Option<Symbol>,
+) -> Result<u32, Error> {
+    let sub = custom_subscription(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        0,
+        cadence,
+        usage_enabled,
+        0,
+        None,
+    )?;
+    store_new_subscription(env, sub, coupon)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn custom_subscription(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    cadence: BillingCadence,
+    usage_enabled: bool,
+    trial_seconds: u64,
+    trial_amount: Option<i128>,
+) -> Result<Subscription, Error> {
     subscriber.require_auth();
+    merchant.require_auth();
     validate_non_negative(amount)?;
-    let sub = Subscription {
-        subscriber: subscriber.clone(),
-        merchant: merchant.clone(),
+    new_subscription(
+        env,
+        subscriber,
+        merchant,
         amount,
         interval_seconds,
-        last_payment_timestamp: env.ledger().timestamp(),
+        cadence,
+        usage_enabled,
+        None,
+        trial_seconds,
+        trial_amount,
+    )
+}
+
+/// A new `Active` subscription starting now, with its schedule and trial terms
+/// validated and its first charge anchored (see [`crate::trials`]). Every
+/// creation path builds on this; the caller checks authorization.
+#[allow(clippy::too_many_arguments)]
+fn new_subscription(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    cadence: BillingCadence,
+    usage_enabled: bool,
+    plan_id: Option<u32>,
+    trial_seconds: u64,
+    trial_amount: Option<i128>,
+) -> Result<Subscription, Error> {
+    let now = env.ledger().timestamp();
+    let (trial_ends_at, trial_amount) = trial_terms(now, amount, trial_seconds, trial_amount)?;
+    let billing_anchor = if cadence == BillingCadence::Fixed {
+        if interval_seconds == 0 {
+            return Err(Error::InvalidInterval);
+        }
+        first_charge_at(now, interval_seconds, trial_ends_at, trial_amount)?
+    } else {
+        validate_cadence(&cadence)?;
+        first_calendar_due(&cadence, now)?
+    };
+    // Nothing is paid yet: the last payment must fall before the first period.
+    let last_payment_timestamp = if billing_anchor > now { now } else { 0 };
+    Ok(Subscription {
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        last_payment_timestamp,
+        billing_anchor,
+        anchor_period: 0,
+        cadence,
         status: SubscriptionStatus::Active,
         prepaid_balance: 0i128,
         usage_enabled,
-    };
+        plan_id,
+        trial_ends_at,
+        trial_amount,
+        discount: Discount::None,
+        discount_cycles_left: 0,
+        failed_attempts: 0,
+        last_failed_at: 0,
+        max_cycles: None,
+        ends_at: None,
+        cycles_charged: 0,
+        cancel_at_period_end: false,
+        paused_at: None,
+        resume_at: None,
+        total_charged: 0,
+        total_refunded: 0,
+        usage_in_arrears: false,
+        pending_usage: 0,
+        price_scheduled_at: None,
+    })
+}
+
+/// Subscribes to a merchant's plan. Price, interval, usage flag and trial come
+/// from the plan; `coupon` is redeemed against the plan merchant's registry.
+pub fn do_create_subscription_from_plan(
+    env: &Env,
+    subscriber: Address,
+    plan_id: u32,
+    coupon: Option<Symbol>,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let plan = get_plan(env, plan_id)?;
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+    let amount = plan_amount_at(env, &plan, env.ledger().timestamp());
+    // The plan's trial price is capped at the current plan price.
+    let trial_amount = Some(plan.trial_amount.min(amount));
+    let sub = new_subscription(
+        env,
+        subscriber.clone(),
+        plan.merchant,
+        amount,
+        plan.interval_seconds,
+        BillingCadence::Fixed,
+        plan.usage_enabled,
+        Some(plan_id),
+        plan.trial_seconds,
+        trial_amount,
+    )?;
+    let id = store_new_subscription(env, sub, coupon)?;
+    env.events().publish(
+        (Symbol::new(env, "subscribed"), plan_id),
+        (id, subscriber, amount),
+    );
+    Ok(id)
+}
+
+fn store_new_subscription(
+    env: &Env,
+    mut sub: Subscription,
+    coupon: Option<Symbol>,
+) -> Result<u32, Error> {
+    if let Some(code) = coupon {
+        let (discount, cycles) = redeem_coupon(env, &sub.merchant, &sub.subscriber, code)?;
+        sub.discount = discount;
+        sub.discount_cycles_left = cycles;
+    }
+
     let id = next_id(env);
-    env.storage().instance().set(&id, &sub);
+    save_subscription(env, id, &sub);
 
-    // Maintain merchant → subscription-ID index
-    let key = DataKey::MerchantSubs(sub.merchant.clone());
-    let mut ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
-    ids.push_back(id);
-    env.storage().instance().set(&key, &ids);
+    // Maintain merchant and subscriber → subscription-ID indices
+    push_merchant_subscription(env, &sub.merchant, id);
+    push_subscriber_subscription(env, &sub.subscriber, id);
 
     Ok(id)
 }
@@ -71,11 +314,17 @@ pub fn do_deposit_funds(
     let token_client = soroban_sdk::token::Client::new(env, &token_addr);
 
     token_client.transfer(&subscriber, &env.current_contract_address(), &amount);
-    env.storage().instance().set(&subscription_id, &sub);
+    save_subscription(env, subscription_id, &sub);
+    increase_prepaid_liabilities(env, amount)?;
     env.events().publish(
         (Symbol::new(env, "deposited"), subscription_id),
         (subscriber, amount, sub.prepaid_balance),
     );
+
+    // Topping up a failed subscription settles the overdue charge right away.
+    if sub.status == SubscriptionStatus::InsufficientBalance {
+        reactivate_after_deposit(env, subscription_id)?;
+    }
     Ok(())
 }
 
@@ -84,18 +333,29 @@ pub fn do_cancel_subscription(
     subscription_id: u32,
     authorizer: Address,
 ) -> Result<(), Error> {
-    authorizer.require_auth();
-
     let mut sub = get_subscription(env, subscription_id)?;
-
-    if authorizer != sub.subscriber && authorizer != sub.merchant {
-        return Err(Error::Unauthorized);
-    }
+    require_lifecycle_auth(env, &sub, &authorizer, LifecycleAction::Cancel)?;
 
     validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+    // A scheduled cancellation applied on load still has its usage to settle.
+    settle_pending_usage(env, subscription_id, &mut sub)?;
+    let refund_amount = if sub.status == SubscriptionStatus::Cancelled {
+        0
+    } else {
+        refund_unused_period(env, subscription_id, &mut sub, env.ledger().timestamp())?
+    };
     sub.status = SubscriptionStatus::Cancelled;
+    sub.cancel_at_period_end = false;
 
-    env.storage().instance().set(&subscription_id, &sub);
+    save_subscription(env, subscription_id, &sub);
+    env.events().publish(
+        (symbol_short!("cancelled"),),
+        SubscriptionCancelledEvent {
+            subscription_id,
+            authorizer,
+            refund_amount,
+        },
+    );
     Ok(())
 }
 
@@ -104,14 +364,7 @@ pub fn do_pause_subscription(
     subscription_id: u32,
     authorizer: Address,
 ) -> Result<(), Error> {
-    authorizer.require_auth();
-
-    let mut sub = get_subscription(env, subscription_id)?;
-    validate_status_transition(&sub.status, &SubscriptionStatus::Paused)?;
-    sub.status = SubscriptionStatus::Paused;
-
-    env.storage().instance().set(&subscription_id, &sub);
-    Ok(())
+    do_pause_until(env, subscription_id, authorizer, None)
 }
 
 pub fn do_resume_subscription(
@@ -119,13 +372,16 @@ pub fn do_resume_subscription(
     subscription_id: u32,
     authorizer: Address,
 ) -> Result<(), Error> {
-    authorizer.require_auth();
-
     let mut sub = get_subscription(env, subscription_id)?;
-    validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
-    sub.status = SubscriptionStatus::Active;
+    require_lifecycle_auth(env, &sub, &authorizer, LifecycleAction::Resume)?;
+    if sub.status == SubscriptionStatus::Paused {
+        resume_billing_clock(&mut sub, env.ledger().timestamp())?;
+    } else {
+        validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
+        sub.status = SubscriptionStatus::Active;
+    }
 
-    env.storage().instance().set(&subscription_id, &sub);
+    save_subscription(env, subscription_id, &sub);
     Ok(())
 }
 
@@ -142,14 +398,20 @@ pub fn do_withdraw_subscriber_funds(
         return Err(Error::Unauthorized);
     }
 
-    if sub.status != SubscriptionStatus::Cancelled {
+    if !matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed
+    ) {
         return Err(Error::InvalidStatusTransition); // Or Unauthorized/InvalidState
     }
+    // Usage owed to the merchant is paid before the rest goes back.
+    settle_pending_usage(env, subscription_id, &mut sub)?;
 
     let amount_to_refund = sub.prepaid_balance;
+    sub.prepaid_balance = 0;
+    save_subscription(env, subscription_id, &sub);
     if amount_to_refund > 0 {
-        sub.prepaid_balance = 0;
-        env.storage().instance().set(&subscription_id, &sub);
+        decrease_prepaid_liabilities(env, amount_to_refund)?;
 
         let token_addr: Address = env
             .storage()
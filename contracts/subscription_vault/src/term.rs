@@ -0,0 +1,66 @@
+//! Fixed-term subscriptions: a maximum number of charges and/or an end date.
+//!
+//! **PRs that only change how fixed terms end should edit this file only.**
+//!
+//! A subscription with `max_cycles` completes after that many interval charges;
+//! one with `ends_at` completes once the next billing period would start at or
+//! after `ends_at`. Completion happens as part of the charge that finishes the
+//! term, or, if that charge never ran, when a charge is attempted past the term
+//! (which charges nothing and reports `ChargeOutcome::TermEnded`). `Completed`
+//! is terminal: the subscriber can withdraw the remaining prepaid balance, as
+//! after cancellation.
+
+use crate::postpaid::settle_pending_usage;
+use crate::schedule::next_charge_due;
+use crate::state_machine::validate_status_transition;
+use crate::storage::save_subscription;
+use crate::types::{Error, Subscription, SubscriptionStatus};
+use soroban_sdk::{Env, Symbol};
+
+/// Validates fixed-term limits for a subscription created at `now`.
+pub fn validate_term(max_cycles: Option<u32>, ends_at: Option<u64>, now: u64) -> Result<(), Error> {
+    if max_cycles == Some(0) || ends_at.is_some_and(|end| end <= now) {
+        return Err(Error::InvalidTerm);
+    }
+    Ok(())
+}
+
+/// Whether the billing period starting at `period_start` lies outside the term.
+pub fn beyond_term(sub: &Subscription, period_start: u64) -> bool {
+    sub.max_cycles.is_some_and(|max| sub.cycles_charged >= max)
+        || sub.ends_at.is_some_and(|end| period_start >= end)
+}
+
+/// Counts a settled interval charge and completes the subscription if no
+/// further period falls inside its term. Returns `true` if it completed.
+pub fn record_cycle(sub: &mut Subscription) -> Result<bool, Error> {
+    sub.cycles_charged = sub.cycles_charged.saturating_add(1);
+    let ended = (sub.max_cycles.is_some() || sub.ends_at.is_some())
+        && beyond_term(sub, next_charge_due(sub)?);
+    if ended {
+        complete(sub)?;
+    }
+    Ok(ended)
+}
+
+fn complete(sub: &mut Subscription) -> Result<(), Error> {
+    validate_status_transition(&sub.status, &SubscriptionStatus::Completed)?;
+    sub.status = SubscriptionStatus::Completed;
+    Ok(())
+}
+
+/// Completes a subscription found past its term when a charge is attempted.
+pub fn end_term(env: &Env, subscription_id: u32, sub: &mut Subscription) -> Result<(), Error> {
+    complete(sub)?;
+    settle_pending_usage(env, subscription_id, sub)?;
+    save_subscription(env, subscription_id, sub);
+    emit_completed(env, subscription_id, sub);
+    Ok(())
+}
+
+pub fn emit_completed(env: &Env, subscription_id: u32, sub: &Subscription) {
+    env.events().publish(
+        (Symbol::new(env, "completed"), subscription_id),
+        (sub.subscriber.clone(), sub.cycles_charged),
+    );
+}
@@ -0,0 +1,106 @@
+//! Trial periods: free or discounted billing until the trial ends.
+//!
+//! **PRs that only change trial behaviour should edit this file only.**
+//!
+//! Each charge pays in advance for the billing period starting at its due
+//! time. Without a trial the first period starts one interval after creation.
+//! A trial starts billing at creation instead:
+//! - a free trial (`trial_amount == 0`) anchors billing at `trial_ends_at`, so
+//!   the first charge falls when the trial ends and is the first paid one;
+//! - a discounted trial anchors billing at creation, and every period starting
+//!   before `trial_ends_at` is billed at `trial_amount`.
+//!
+//! The first full-price charge clears the trial and emits `trial_converted`.
+
+use crate::plans::{get_owned_plan, save_plan};
+use crate::types::{Error, Subscription};
+use soroban_sdk::{Address, Env, Symbol};
+
+/// Validates trial terms and returns `(trial_ends_at, trial_amount)` for a
+/// subscription starting at `now`. `trial_seconds == 0` means no trial;
+/// `trial_amount == None` means a free trial.
+pub fn trial_terms(
+    now: u64,
+    amount: i128,
+    trial_seconds: u64,
+    trial_amount: Option<i128>,
+) -> Result<(Option<u64>, i128), Error> {
+    let trial_amount = trial_amount.unwrap_or(0);
+    if trial_amount < 0 || trial_amount > amount {
+        return Err(Error::InvalidAmount);
+    }
+    if trial_seconds == 0 {
+        return Ok((None, 0));
+    }
+    let ends_at = now.checked_add(trial_seconds).ok_or(Error::Overflow)?;
+    Ok((Some(ends_at), trial_amount))
+}
+
+/// Billing anchor (first charge time) for a subscription created at `now`:
+/// one interval after creation without a trial, the trial end for a free
+/// trial, and creation itself for a discounted trial.
+pub fn first_charge_at(
+    now: u64,
+    interval_seconds: u64,
+    trial_ends_at: Option<u64>,
+    trial_amount: i128,
+) -> Result<u64, Error> {
+    match trial_ends_at {
+        Some(ends_at) if trial_amount == 0 => Ok(ends_at),
+        Some(_) => Ok(now),
+        None => now.checked_add(interval_seconds).ok_or(Error::Overflow),
+    }
+}
+
+/// Trial price for the period starting at `period_start`, or `None` if it is
+/// billed at full price, i.e. if it starts after the trial ended.
+pub fn trial_price(sub: &Subscription, period_start: u64) -> Option<i128> {
+    match sub.trial_ends_at {
+        Some(ends_at) if period_start < ends_at => Some(sub.trial_amount),
+        _ => None,
+    }
+}
+
+/// Whether `sub` is still in its trial at `timestamp`.
+pub fn in_trial(sub: &Subscription, timestamp: u64) -> bool {
+    sub.trial_ends_at.is_some_and(|ends_at| timestamp < ends_at)
+}
+
+/// Clears the trial on `sub`. Returns `true` if it was in trial.
+pub fn end_trial(sub: &mut Subscription) -> bool {
+    if sub.trial_ends_at.is_none() {
+        return false;
+    }
+    sub.trial_ends_at = None;
+    sub.trial_amount = 0;
+    true
+}
+
+pub fn emit_trial_converted(env: &Env, subscription_id: u32, sub: &Subscription) {
+    env.events().publish(
+        (Symbol::new(env, "trial_converted"), subscription_id),
+        (sub.subscriber.clone(), sub.amount),
+    );
+}
+
+/// Sets the trial offered to new subscribers of a plan. `trial_seconds == 0`
+/// removes the trial; existing subscriptions keep the trial they started with.
+pub fn do_set_plan_trial(
+    env: &Env,
+    merchant: Address,
+    plan_id: u32,
+    trial_seconds: u64,
+    trial_amount: Option<i128>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let mut plan = get_owned_plan(env, &merchant, plan_id)?;
+    let (_, trial_amount) = trial_terms(0, plan.amount, trial_seconds, trial_amount)?;
+    plan.trial_seconds = trial_seconds;
+    plan.trial_amount = trial_amount;
+    save_plan(env, &plan);
+    env.events().publish(
+        (Symbol::new(env, "plan_trial_set"), merchant),
+        (plan_id, trial_seconds, trial_amount),
+    );
+    Ok(())
+}
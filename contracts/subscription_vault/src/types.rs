@@ -3,14 +3,81 @@
 //! Kept in a separate module to reduce merge conflicts when editing state machine
 //! or contract entrypoints.
 
-use soroban_sdk::{contracterror, contracttype, Address};
+use soroban_sdk::{contracterror, contracttype, Address, BytesN, String, Symbol, Vec};
 
-/// Storage keys for secondary indices.
+/// Storage keys. See [`crate::storage`] for which storage tier each key lives in.
 #[contracttype]
 #[derive(Clone)]
 pub enum DataKey {
-    /// Maps a merchant address to its list of subscription IDs.
-    MerchantSubs(Address),
+    /// A subscription record by ID.
+    Subscription(u32),
+    /// Last charged billing period index for a subscription (replay protection).
+    ChargedPeriod(u32),
+    /// Last idempotency key accepted for a subscription.
+    IdemKey(u32),
+    /// Number of subscriptions indexed for a merchant.
+    MerchantSubCount(Address),
+    /// The n-th subscription ID created for a merchant (insertion order).
+    MerchantSubAt(Address, u32),
+    /// Number of subscriptions indexed for a subscriber.
+    SubscriberSubCount(Address),
+    /// The n-th subscription ID created by a subscriber (ascending ID order).
+    SubscriberSubAt(Address, u32),
+    /// Merchant's claimable balance, credited by charges and debited by withdrawals.
+    MerchantBalance(Address),
+    /// Running total of all subscriptions' `prepaid_balance`.
+    TotalPrepaid,
+    /// Running total of all merchants' claimable balances.
+    TotalMerchantBalances,
+    /// A merchant plan by ID.
+    Plan(u32),
+    /// Number of plans created by a merchant.
+    MerchantPlanCount(Address),
+    /// The n-th plan ID created by a merchant (creation order).
+    MerchantPlanAt(Address, u32),
+    /// Scheduled price change for every subscription on a plan.
+    PlanPriceChange(u32),
+    /// Scheduled price change for a single subscription.
+    SubPriceChange(u32),
+    /// Coupon registered by a merchant under a code.
+    Coupon(Address, Symbol),
+    /// What the subscription's most recent interval charge billed.
+    LastCharge(u32),
+    /// Merchant-specific dunning policy (overrides the global one).
+    MerchantDunning(Address),
+    /// Merchant's policy for periods missed without a charge.
+    MerchantCatchUp(Address),
+    /// Merchant's maximum pause length in seconds.
+    MerchantMaxPause(Address),
+    /// Merchant's policy for who may pause, resume and cancel.
+    MerchantAuthPolicy(Address),
+    /// Whether a merchant refunds the unused part of the period on cancellation.
+    MerchantCancelRefund(Address),
+    /// Subscriber's ceilings for one-off charges on a subscription.
+    OneOffLimits(u32),
+    /// One-off charges made in the subscription's current billing period.
+    OneOffSpend(u32),
+    /// Subscriber's usage spending caps on a subscription.
+    UsageCaps(u32),
+    /// Usage charged in the subscription's current billing period.
+    UsageSpend(u32),
+    /// Usage rate card attached to a plan.
+    PlanRateCard(u32),
+    /// Units reported for the subscription's current billing period.
+    UnitUsage(u32),
+    /// Usage meter registered for all of a merchant's subscriptions.
+    MerchantMeter(Address, Address),
+    /// Usage meter registered for the subscriptions of one plan.
+    PlanMeter(u32, Address),
+    /// Receipt-signing meter key registered for all of a merchant's subscriptions.
+    MerchantMeterKey(Address, BytesN<32>),
+    /// Receipt-signing meter key registered for the subscriptions of one plan.
+    PlanMeterKey(u32, BytesN<32>),
+    /// Refundable amount charged per billing period, for a subscription's most
+    /// recent periods.
+    PeriodCharges(u32),
+    /// Last receipt nonce accepted from a meter key.
+    ReceiptNonce(BytesN<32>),
 }
 
 #[contracterror]
@@ -19,7 +86,7 @@ pub enum DataKey {
 pub enum Error {
     NotFound = 404,
     Unauthorized = 401,
-    /// Charge attempted before `last_payment_timestamp + interval_seconds`.
+    /// Charge attempted before the next billing period is due.
     IntervalNotElapsed = 1001,
     /// Subscription is not Active (e.g. Paused, Cancelled).
     NotActive = 1002,
@@ -41,6 +108,46 @@ pub enum Error {
     Replay = 1007,
     /// Recovery amount is zero or negative.
     InvalidRecoveryAmount = 1008,
+    /// Recovery amount exceeds the contract's surplus over tracked liabilities.
+    ExceedsSurplus = 1011,
+    /// Billing interval is zero, or a calendar cadence names a day or month that never occurs.
+    InvalidInterval = 1012,
+    /// Plan has been deactivated by its merchant and accepts no new subscribers.
+    PlanInactive = 1013,
+    /// Price change `effective_at` is sooner than the required notice period.
+    NoticePeriodTooShort = 1014,
+    /// Coupon is deactivated or expired.
+    CouponUnavailable = 1015,
+    /// Coupon has reached its redemption cap.
+    CouponExhausted = 1016,
+    /// Merchant already registered a coupon with this code.
+    CouponExists = 1017,
+    /// Retry of a failed charge attempted before the policy's retry spacing elapsed.
+    RetryNotDue = 1018,
+    /// The subscription's fixed term is over; no further periods are billed.
+    TermEnded = 1019,
+    /// Fixed term with zero cycles or an end date that is not in the future.
+    InvalidTerm = 1020,
+    /// Pause `resume_at` is not in the future or exceeds the merchant's maximum pause length.
+    InvalidPause = 1021,
+    /// Refund would take refunds above what the subscription, or the refunded period, was charged.
+    RefundExceedsCharged = 1022,
+    /// Refund names a billing period that was never charged.
+    RefundPeriodNotCharged = 1023,
+    /// One-off charge exceeds the subscriber's per-charge or per-period ceiling.
+    OneOffLimitExceeded = 1024,
+    /// Usage receipt was submitted after its expiry.
+    ReceiptExpired = 1025,
+    /// Usage receipt is for a billing period other than the current one.
+    ReceiptPeriodMismatch = 1026,
+    /// Usage charge exceeds the subscriber's per-period or per-call usage cap.
+    UsageCapExceeded = 1027,
+    /// Rate card has a negative price or tiers not in ascending order.
+    InvalidRateCard = 1028,
+    /// The subscription's plan has no rate card, so units cannot be priced.
+    NoRateCard = 1029,
+    /// Lifecycle auth policy does not let the subscriber cancel.
+    InvalidAuthPolicy = 1030,
 }
 
 impl Error {
@@ -61,6 +168,57 @@ impl Error {
             Error::InvalidAmount => 1006,
             Error::Replay => 1007,
             Error::InvalidRecoveryAmount => 1008,
+            Error::ExceedsSurplus => 1011,
+            Error::InvalidInterval => 1012,
+            Error::PlanInactive => 1013,
+            Error::NoticePeriodTooShort => 1014,
+            Error::CouponUnavailable => 1015,
+            Error::CouponExhausted => 1016,
+            Error::CouponExists => 1017,
+            Error::RetryNotDue => 1018,
+            Error::TermEnded => 1019,
+            Error::InvalidTerm => 1020,
+            Error::InvalidPause => 1021,
+            Error::RefundExceedsCharged => 1022,
+            Error::RefundPeriodNotCharged => 1023,
+            Error::OneOffLimitExceeded => 1024,
+            Error::ReceiptExpired => 1025,
+            Error::ReceiptPeriodMismatch => 1026,
+            Error::UsageCapExceeded => 1027,
+            Error::InvalidRateCard => 1028,
+            Error::NoRateCard => 1029,
+            Error::InvalidAuthPolicy => 1030,
+        }
+    }
+}
+
+/// How a charge attempt ended when it did not fail with an [`Error`].
+///
+/// A payment the prepaid balance cannot cover is an outcome rather than an
+/// error, so the failed attempt (and any dunning cancellation) is kept.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ChargeOutcome {
+    /// The period was charged (or the idempotency key was already processed).
+    Charged,
+    /// The balance fell short; the failed attempt is recorded and the
+    /// subscription is `InsufficientBalance`.
+    InsufficientBalance,
+    /// The dunning policy is exhausted; the subscription is `Cancelled`.
+    DunningCancelled,
+    /// The period lies past the fixed term; the subscription is `Completed`
+    /// without being charged.
+    TermEnded,
+}
+
+impl ChargeOutcome {
+    /// Code reported for this outcome in a [`BatchChargeResult`]; 0 if charged.
+    pub const fn to_code(&self) -> u32 {
+        match self {
+            ChargeOutcome::Charged => 0,
+            ChargeOutcome::InsufficientBalance => Error::InsufficientBalance.to_code(),
+            ChargeOutcome::DunningCancelled => Error::NotActive.to_code(),
+            ChargeOutcome::TermEnded => Error::TermEnded.to_code(),
         }
     }
 }
@@ -82,7 +240,7 @@ pub struct BatchChargeResult {
 /// The subscription status follows a defined state machine with specific allowed transitions:
 ///
 /// - **Active**: Subscription is active and charges can be processed.
-///   - Can transition to: `Paused`, `Cancelled`, `InsufficientBalance`
+///   - Can transition to: `Paused`, `Cancelled`, `InsufficientBalance`, `Completed`
 ///
 /// - **Paused**: Subscription is temporarily suspended, no charges are processed.
 ///   - Can transition to: `Active`, `Cancelled`
@@ -91,7 +249,10 @@ pub struct BatchChargeResult {
 ///   - No outgoing transitions (terminal state)
 ///
 /// - **InsufficientBalance**: Subscription failed due to insufficient funds.
-///   - Can transition to: `Active` (after deposit), `Cancelled`
+///   - Can transition to: `Active` (after deposit), `Cancelled`, `Completed`
+///
+/// - **Completed**: A fixed-term subscription ran its full term.
+///   - No outgoing transitions (terminal state)
 ///
 /// Invalid transitions (e.g., `Cancelled` -> `Active`) are rejected with
 /// [`Error::InvalidStatusTransition`].
@@ -106,6 +267,8 @@ pub enum SubscriptionStatus {
     Cancelled = 2,
     /// Subscription failed due to insufficient balance for charging.
     InsufficientBalance = 3,
+    /// Fixed term (`max_cycles` or `ends_at`) is over (terminal state).
+    Completed = 4,
 }
 
 /// Stores subscription details and current state.
@@ -118,12 +281,193 @@ pub struct Subscription {
     pub subscriber: Address,
     pub merchant: Address,
     pub amount: i128,
+    /// Length of a `Fixed` billing period; 0 for calendar cadences.
     pub interval_seconds: u64,
+    /// Time of the last interval charge. Until the first one, the creation
+    /// time, or 0 when billing starts at creation (a discounted trial).
     pub last_payment_timestamp: u64,
+    /// Start of billing period `anchor_period` (the first charge time after
+    /// creation or a plan change); see [`crate::schedule`].
+    pub billing_anchor: u64,
+    /// Number of the billing period starting at `billing_anchor`.
+    pub anchor_period: u64,
+    /// How billing periods follow the anchor.
+    pub cadence: BillingCadence,
     /// Current lifecycle state. Modified only through state machine transitions.
     pub status: SubscriptionStatus,
     pub prepaid_balance: i128,
     pub usage_enabled: bool,
+    /// Plan this subscription was created from, or `None` for a custom agreement.
+    pub plan_id: Option<u32>,
+    /// End of the trial period, or `None` when not (or no longer) in trial.
+    pub trial_ends_at: Option<u64>,
+    /// Price per interval during the trial; 0 for a free trial.
+    pub trial_amount: i128,
+    /// Coupon discount applied to full-price charges while cycles remain.
+    pub discount: Discount,
+    /// Full-price charges the discount still applies to.
+    pub discount_cycles_left: u32,
+    /// Consecutive failed charge attempts; reset by a successful charge.
+    pub failed_attempts: u32,
+    /// Time of the last failed charge attempt (0 if none).
+    pub last_failed_at: u64,
+    /// Number of interval charges after which the subscription completes.
+    pub max_cycles: Option<u32>,
+    /// No billing period starting at or after this time is charged.
+    pub ends_at: Option<u64>,
+    /// Interval charges made so far.
+    pub cycles_charged: u32,
+    /// Cancel once the current paid period ends; see [`crate::cancellation`].
+    pub cancel_at_period_end: bool,
+    /// When the current pause started; `None` unless paused.
+    pub paused_at: Option<u64>,
+    /// When the current pause ends automatically; see [`crate::pause`].
+    pub resume_at: Option<u64>,
+    /// Net amount billed to the subscription's merchant so far.
+    pub total_charged: i128,
+    /// Amount the merchant has refunded; never exceeds `total_charged`.
+    pub total_refunded: i128,
+    /// Usage accrues into `pending_usage` and is settled with the next
+    /// interval charge instead of being debited when reported.
+    pub usage_in_arrears: bool,
+    /// Usage reported in arrears and not yet settled; see [`crate::postpaid`].
+    pub pending_usage: i128,
+    /// `scheduled_at` of the last price change applied; changes scheduled
+    /// before it no longer apply. See [`crate::pricing`].
+    pub price_scheduled_at: Option<u64>,
+}
+
+/// How a subscription's billing periods are laid out after its `billing_anchor`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BillingCadence {
+    /// Every `interval_seconds`.
+    Fixed,
+    /// Monthly on the given day of the month (1–31), clamped to shorter months.
+    MonthlyOnDay(u32),
+    /// Yearly on the given month (1–12) and day, clamped in shorter Februaries.
+    YearlyOnDate(u32, u32),
+}
+
+/// A merchant-defined price list entry that subscribers can subscribe to by ID.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Plan {
+    pub plan_id: u32,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub usage_enabled: bool,
+    /// Optional human-readable name.
+    pub name: Option<String>,
+    /// Optional hash of off-chain plan metadata (terms, feature list).
+    pub metadata_hash: Option<BytesN<32>>,
+    /// Inactive plans accept no new subscribers.
+    pub active: bool,
+    /// Trial length offered to new subscribers; 0 for no trial.
+    pub trial_seconds: u64,
+    /// Price per interval during the trial; 0 for a free trial.
+    pub trial_amount: i128,
+}
+
+/// Discount granted by a coupon.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Discount {
+    /// No discount. Used on subscriptions without a running coupon
+    /// (`Option` of a custom type cannot be stored in a struct field).
+    None,
+    /// Percentage off in basis points (10_000 = 100%).
+    PercentBps(u32),
+    /// Fixed amount off each charge (floored at zero).
+    Fixed(i128),
+}
+
+/// A merchant-issued promotion code.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Coupon {
+    pub merchant: Address,
+    pub code: Symbol,
+    pub discount: Discount,
+    /// Number of full-price billing cycles the discount applies to.
+    pub cycles: u32,
+    /// Maximum number of redemptions; 0 for unlimited.
+    pub max_redemptions: u32,
+    pub redemptions: u32,
+    /// Redemptions are rejected at or after this time.
+    pub expires_at: Option<u64>,
+    pub active: bool,
+}
+
+/// How failed interval charges are retried before the subscription is cancelled.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DunningPolicy {
+    /// How long after the failed charge was due retries are allowed.
+    pub grace_period_seconds: u64,
+    /// Retries allowed after the original failed attempt.
+    pub max_retries: u32,
+    /// Minimum spacing between attempts.
+    pub retry_interval_seconds: u64,
+}
+
+/// Which missed billing periods `charge_subscription_catch_up` charges.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CatchUpPolicy {
+    /// Missed periods are forgiven; only the current period is charged.
+    Forgive,
+    /// Every missed period is charged.
+    All,
+    /// At most this many of the most recent missed periods are charged; older
+    /// ones are forgiven.
+    UpTo(u32),
+}
+
+/// A party that may be allowed to act on a subscription.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Party {
+    Subscriber,
+    Merchant,
+    /// The contract admin.
+    Admin,
+    /// The policy's delegated `manager` address.
+    Manager,
+}
+
+/// Which parties may pause, resume and cancel a merchant's subscriptions.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LifecycleAuthPolicy {
+    pub pause: Vec<Party>,
+    pub resume: Vec<Party>,
+    pub cancel: Vec<Party>,
+    /// Address acting as `Party::Manager`, if any.
+    pub manager: Option<Address>,
+}
+
+/// The most recent interval charge, used to credit the unused part of the
+/// current period when the subscription changes plan.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LastCharge {
+    /// Amount actually billed for the period (after trial price or discount).
+    pub amount: i128,
+    /// Start of the anchored billing period it paid for.
+    pub period_start: u64,
+}
+
+/// A merchant-announced price change. Applies to billing periods starting at
+/// or after `effective_at`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceChange {
+    pub new_amount: i128,
+    pub effective_at: u64,
+    /// When the change was announced; the latest announcement wins on conflict.
+    pub scheduled_at: u64,
 }
 
 // Event types
@@ -150,7 +494,29 @@ pub struct FundsDepositedEvent {
 pub struct SubscriptionChargedEvent {
     pub subscription_id: u32,
     pub merchant: Address,
+    /// Total debited, including `usage_amount`.
     pub amount: i128,
+    /// Postpaid usage settled with this charge.
+    pub usage_amount: i128,
+}
+
+/// Emitted when a subscription moves to another plan mid-cycle.
+///
+/// `net_amount = charged - credit`: positive amounts were debited from the
+/// prepaid balance, negative amounts were returned to it.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct PlanChangedEvent {
+    pub subscription_id: u32,
+    pub old_plan_id: Option<u32>,
+    pub new_plan_id: u32,
+    pub old_amount: i128,
+    pub new_amount: i128,
+    /// Unused share of what was paid for the current period.
+    pub credit: i128,
+    /// Charge for the new period starting at the change.
+    pub charged: i128,
+    pub net_amount: i128,
 }
 
 #[contracttype]
@@ -158,6 +524,7 @@ pub struct SubscriptionChargedEvent {
 pub struct SubscriptionCancelledEvent {
     pub subscription_id: u32,
     pub authorizer: Address,
+    /// Prorated refund moved back into `prepaid_balance` (0 without the merchant's refund policy).
     pub refund_amount: i128,
 }
 
@@ -191,6 +558,119 @@ pub struct OneOffChargedEvent {
     pub amount: i128,
 }
 
+/// A usage report signed by a meter key; see [`crate::receipts`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UsageReceipt {
+    pub subscription_id: u32,
+    pub usage_amount: i128,
+    /// Must exceed the last nonce accepted from the same meter key.
+    pub nonce: u64,
+    /// Billing period index the usage belongs to.
+    pub period: u64,
+    /// Last timestamp at which the receipt may be submitted.
+    pub expires_at: u64,
+}
+
+/// Subscriber-approved ceilings for merchant one-off charges.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OneOffLimits {
+    /// Largest single one-off charge.
+    pub per_charge: i128,
+    /// Largest total of one-off charges within one billing period.
+    pub per_period: i128,
+}
+
+/// How tiered unit prices apply; see [`crate::rate_cards`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TierMode {
+    /// Each unit is priced by the tier it falls in.
+    Graduated,
+    /// All units are priced by the tier the period total falls in.
+    Volume,
+}
+
+/// A pricing tier covering units up to and including `up_to`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateTier {
+    pub up_to: u64,
+    pub unit_price: i128,
+}
+
+/// A plan's usage price list.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateCard {
+    /// Price per unit beyond the last tier (or for every unit without tiers).
+    pub unit_price: i128,
+    /// Tiers in ascending `up_to` order; may be empty.
+    pub tiers: Vec<RateTier>,
+    pub mode: TierMode,
+    /// Free units per billing period, before any tier applies.
+    pub included_units: u64,
+}
+
+/// Usage units reported in a billing period and what they were billed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnitUsage {
+    /// Billing period index (`None` before the first period starts).
+    pub period: Option<u64>,
+    pub units: u64,
+    pub amount: i128,
+    /// The plan's rate card when the period's first units were reported; it
+    /// prices the whole period.
+    pub card: RateCard,
+}
+
+/// Subscriber-chosen limits on usage charges.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UsageCaps {
+    /// Largest total of usage charges within one billing period.
+    pub per_period: i128,
+    /// Largest single usage charge, if limited.
+    pub per_call: Option<i128>,
+}
+
+/// Amount spent so far in a billing period (one-off charges or usage).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PeriodSpend {
+    /// Billing period index (`None` before the first period starts).
+    pub period: Option<u64>,
+    pub amount: i128,
+}
+
+/// Where a merchant refund is paid.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RefundDestination {
+    /// Credited to the subscription's `prepaid_balance`.
+    PrepaidBalance,
+    /// Transferred directly to the subscriber's wallet.
+    Subscriber,
+}
+
+/// Emitted when a merchant refunds part of what a subscription was charged.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct RefundIssuedEvent {
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub amount: i128,
+    /// Billing period index of the charge being refunded.
+    pub period: u64,
+    /// Merchant-defined reason code.
+    pub reason: u32,
+    pub destination: RefundDestination,
+    /// Refunded to date, including this refund.
+    pub total_refunded: i128,
+}
+
 /// Represents the reason for stranded funds that can be recovered by admin.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -219,6 +699,27 @@ pub struct RecoveryEvent {
     pub timestamp: u64,
 }
 
+/// Snapshot of the contract's token holdings versus what it owes.
+///
+/// Returned by [`crate::SubscriptionVault::get_solvency_report`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolvencyReport {
+    /// Token balance actually held by the contract.
+    pub token_balance: i128,
+    /// Sum of all subscriptions' prepaid balances.
+    pub total_prepaid: i128,
+    /// Sum of all merchants' claimable balances.
+    pub total_merchant_balances: i128,
+    /// `total_prepaid + total_merchant_balances`.
+    pub total_liabilities: i128,
+    /// `token_balance - total_liabilities`; the only amount admin may recover.
+    /// Negative if the contract is under-collateralised.
+    pub surplus: i128,
+    /// True if `surplus >= 0`.
+    pub is_solvent: bool,
+}
+
 /// Result of computing next charge information for a subscription.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -227,4 +728,6 @@ pub struct NextChargeInfo {
     pub next_charge_timestamp: u64,
     /// Whether a charge is actually expected based on the subscription status.
     pub is_charge_expected: bool,
+    /// Amount that charge will bill, after any trial price or coupon discount.
+    pub amount: i128,
 }
@@ -0,0 +1,125 @@
+//! Subscriber-chosen caps on usage charges.
+//!
+//! **PRs that only change usage spending limits should edit this file only.**
+//!
+//! Without caps a usage charge may take the whole prepaid balance. A
+//! subscriber can limit usage spend per billing period and, optionally, per
+//! call. Usage is tracked for the anchored billing period containing each
+//! charge (time before the first period counts as one period of its own), so
+//! the allowance resets when the next period starts. The caps apply to
+//! `charge_usage` and to usage receipts alike.
+
+use crate::queries::get_subscription;
+use crate::safe_math::{safe_add_balance, safe_sub, validate_non_negative};
+use crate::schedule::period_at;
+use crate::storage::{read_persistent, write_persistent};
+use crate::types::{DataKey, Error, PeriodSpend, Subscription, UsageCaps};
+use soroban_sdk::{Env, Symbol};
+
+pub fn do_set_usage_caps(
+    env: &Env,
+    subscription_id: u32,
+    per_period: i128,
+    per_call: Option<i128>,
+) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    validate_non_negative(per_period)?;
+    if let Some(per_call) = per_call {
+        validate_non_negative(per_call)?;
+    }
+
+    let caps = UsageCaps {
+        per_period,
+        per_call,
+    };
+    write_persistent(env, &DataKey::UsageCaps(subscription_id), &caps);
+    env.events()
+        .publish((Symbol::new(env, "usage_caps_set"), subscription_id), caps);
+    Ok(())
+}
+
+/// Lifts the subscriber's usage caps.
+pub fn do_remove_usage_caps(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    env.storage()
+        .persistent()
+        .remove(&DataKey::UsageCaps(subscription_id));
+    env.events().publish(
+        (Symbol::new(env, "usage_caps_removed"), subscription_id),
+        (),
+    );
+    Ok(())
+}
+
+pub fn get_usage_caps(env: &Env, subscription_id: u32) -> Option<UsageCaps> {
+    read_persistent(env, &DataKey::UsageCaps(subscription_id))
+}
+
+fn spent_in(env: &Env, subscription_id: u32, period: Option<u64>) -> i128 {
+    read_persistent::<PeriodSpend>(env, &DataKey::UsageSpend(subscription_id))
+        .filter(|spend| spend.period == period)
+        .map_or(0, |spend| spend.amount)
+}
+
+/// Usage that can still be charged in the current period; `None` if uncapped.
+pub fn get_usage_allowance(env: &Env, subscription_id: u32) -> Result<Option<i128>, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let Some(caps) = get_usage_caps(env, subscription_id) else {
+        return Ok(None);
+    };
+    let period = period_at(&sub, env.ledger().timestamp());
+    let spent = spent_in(env, subscription_id, period);
+    Ok(Some(caps.per_period.saturating_sub(spent).max(0)))
+}
+
+/// Checks `amount` against the caps and records it as spent in the current
+/// period. Fails with `UsageCapExceeded` without recording anything.
+pub fn consume_usage_allowance(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    amount: i128,
+) -> Result<(), Error> {
+    let Some(caps) = get_usage_caps(env, subscription_id) else {
+        return Ok(());
+    };
+    let period = period_at(sub, env.ledger().timestamp());
+    let spent = safe_add_balance(spent_in(env, subscription_id, period), amount)?;
+    if spent > caps.per_period || caps.per_call.is_some_and(|max| amount > max) {
+        return Err(Error::UsageCapExceeded);
+    }
+    write_persistent(
+        env,
+        &DataKey::UsageSpend(subscription_id),
+        &PeriodSpend {
+            period,
+            amount: spent,
+        },
+    );
+    Ok(())
+}
+
+/// Gives `amount` of usage credited back in the current period back to the
+/// allowance, stopping at zero spent.
+pub fn release_usage_allowance(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    amount: i128,
+) -> Result<(), Error> {
+    let period = period_at(sub, env.ledger().timestamp());
+    let spent = spent_in(env, subscription_id, period);
+    if spent > 0 {
+        write_persistent(
+            env,
+            &DataKey::UsageSpend(subscription_id),
+            &PeriodSpend {
+                period,
+                amount: safe_sub(spent, amount)?.max(0),
+            },
+        );
+    }
+    Ok(())
+}